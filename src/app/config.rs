@@ -0,0 +1,509 @@
+use std::collections::HashMap;
+use std::io::{Result, Write};
+use std::path::Path;
+
+use serde_json::{Map, Value};
+
+use super::{log, DocsConfig, HttpConfig, SaltConfig};
+
+/// CONFIG_VERSION is the version of the config file layout written by this
+/// version of salt, bump it whenever a migration is added to [migrate]
+pub(crate) const CONFIG_VERSION: u64 = 1;
+
+/// CONFIG_KEYS are the settings which can be changed with `s config`, as
+/// (key, kind, description). Nested settings are written as `parent.key`
+pub(crate) const CONFIG_KEYS: [(&str, &str, &str); 8] = [
+    (
+        "editor",
+        "string",
+        "editor or editor template like `code -g {file}:{line}`, defaults to $VISUAL or $EDITOR",
+    ),
+    (
+        "docs.assets",
+        "string",
+        "where html docs load styles and scripts from, `cdn` or `offline`",
+    ),
+    (
+        "docs.template",
+        "string",
+        "handlebars template for html docs of projects which set none",
+    ),
+    (
+        "docs.css",
+        "string",
+        "css added to html docs of projects which set none",
+    ),
+    (
+        "http.timeout",
+        "number",
+        "seconds a request can take, defaults to 30",
+    ),
+    (
+        "http.proxy",
+        "string",
+        "proxy for every request like `http://proxy:3128`, defaults to the proxy env variables",
+    ),
+    (
+        "http.ca_bundle",
+        "string",
+        "pem file of certificates to trust on top of the system ones",
+    ),
+    (
+        "http.tokens",
+        "map",
+        "tokens sent as bearer auth by host, like `gitlab.corp.io=glpat-x,raw.githubusercontent.com=ghp_x`",
+    ),
+];
+
+impl Default for SaltConfig {
+    fn default() -> Self {
+        SaltConfig {
+            version: CONFIG_VERSION,
+            editor: None,
+            pinned_paths: HashMap::new(),
+            docs: DocsConfig::default(),
+            http: HttpConfig::default(),
+        }
+    }
+}
+
+/// load_config reads the salt config at cfg_path, creating a default one if
+/// there is none. Configs written by older versions of salt are migrated and
+/// written back before they are used
+pub(crate) fn load_config(cfg_path: &Path) -> Result<SaltConfig> {
+    if !cfg_path.exists() {
+        let cfg = SaltConfig::default();
+        write_config(cfg_path, &cfg)?;
+        return Ok(cfg);
+    }
+
+    let cfg_str = strip_json_comments(&std::fs::read_to_string(cfg_path)?);
+    let mut cfg =
+        serde_json::from_str::<Value>(&cfg_str).map_err(|e| config_error(cfg_path, &cfg_str, e))?;
+    if migrate(&mut cfg) {
+        log!("migrated salt config to version {}", CONFIG_VERSION);
+        write_config_value(cfg_path, &cfg)?;
+    }
+    serde_json::from_value::<SaltConfig>(cfg).map_err(|e| {
+        // errors from a json value do not carry a position, parsing the text
+        // again gives us the line to point at
+        match serde_json::from_str::<SaltConfig>(&cfg_str) {
+            Err(located) => config_error(cfg_path, &cfg_str, located),
+            Ok(_) => config_error(cfg_path, &cfg_str, e),
+        }
+    })
+}
+
+pub(crate) fn write_config(cfg_path: &Path, c: &SaltConfig) -> Result<()> {
+    write_config_value(cfg_path, &serde_json::to_value(c)?)
+}
+
+/// write_config_value writes the config into a temporary file first and then
+/// renames it over the config, so a crash midway never leaves a broken config
+pub(crate) fn write_config_value(cfg_path: &Path, c: &Value) -> Result<()> {
+    // if config directory is not there create one
+    if let Some(cfg_dir) = cfg_path.parent() {
+        if !cfg_dir.exists() {
+            log!("config dir not found | creating one");
+            std::fs::create_dir_all(cfg_dir)?;
+        }
+    }
+
+    log!("writing config");
+    let tmp_path = cfg_path.with_extension("tmp");
+    let mut tmp_file = std::fs::File::create(&tmp_path)?;
+    let c = serde_json::to_string_pretty(&c)?;
+    tmp_file.write_all(c.as_bytes())?;
+    tmp_file.sync_all()?;
+    std::fs::rename(tmp_path, cfg_path)
+}
+
+/// update_config edits the config file on disk. The file is handled as plain
+/// json, so keys which are not touched by f are written back as they are,
+/// comments in it are not kept
+pub(crate) fn update_config(
+    cfg_path: &Path,
+    f: impl FnOnce(&mut Map<String, Value>) -> Result<()>,
+) -> Result<()> {
+    let mut cfg = match std::fs::read_to_string(cfg_path) {
+        Ok(cfg_str) => {
+            let cfg_str = strip_json_comments(&cfg_str);
+            serde_json::from_str::<Value>(&cfg_str)
+                .map_err(|e| config_error(cfg_path, &cfg_str, e))?
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            serde_json::to_value(SaltConfig::default())?
+        }
+        Err(e) => return Err(e),
+    };
+    match cfg.as_object_mut() {
+        Some(cfg_map) => f(cfg_map)?,
+        None => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "salt config is not a json object",
+            ))
+        }
+    };
+    write_config_value(cfg_path, &cfg)
+}
+
+/// update_pinned_paths edits the pins inside the config file on disk
+pub(crate) fn update_pinned_paths(
+    cfg_path: &Path,
+    f: impl FnOnce(&mut HashMap<String, String>),
+) -> Result<()> {
+    update_config(cfg_path, |cfg_map| {
+        let mut pinned_paths = match cfg_map.remove("pinned_paths") {
+            Some(Value::Null) | None => HashMap::new(),
+            Some(pins) => serde_json::from_value::<HashMap<String, String>>(pins)?,
+        };
+        f(&mut pinned_paths);
+        log!("pinned paths: {:?}", &pinned_paths);
+        cfg_map.insert("pinned_paths".into(), serde_json::to_value(pinned_paths)?);
+        Ok(())
+    })
+}
+
+/// SECRET_CONFIG_KEYS are the settings whose values are masked when they are
+/// shown by `s config`
+const SECRET_CONFIG_KEYS: [&str; 1] = ["http.tokens"];
+
+const SECRET_MASK: &str = "********";
+
+/// masked_config_value is [get_config_value] with the values of secret
+/// settings replaced, so they can be printed without leaking them. For a map
+/// only its values are masked, the keys are kept
+pub(crate) fn masked_config_value(c: &SaltConfig, key: &str) -> Result<Value> {
+    let value = get_config_value(c, key)?;
+    if !SECRET_CONFIG_KEYS.contains(&key) {
+        return Ok(value);
+    }
+    Ok(match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(k, _)| (k, Value::from(SECRET_MASK)))
+                .collect(),
+        ),
+        Value::Null => Value::Null,
+        _ => Value::from(SECRET_MASK),
+    })
+}
+
+/// get_config_value looks up a setting, dotted keys walk into nested settings
+pub(crate) fn get_config_value(c: &SaltConfig, key: &str) -> Result<Value> {
+    config_key_kind(key)?;
+    let mut value = serde_json::to_value(c)?;
+    for part in key.split('.') {
+        value = match value.get(part) {
+            Some(v) => v.clone(),
+            None => return Ok(Value::Null),
+        };
+    }
+    Ok(value)
+}
+
+/// set_config_value parses value according to the kind of the setting and
+/// writes it into the config file. A value of None removes the setting so
+/// that it falls back to its default
+pub(crate) fn set_config_value(cfg_path: &Path, key: &str, value: Option<&str>) -> Result<()> {
+    let kind = config_key_kind(key)?;
+    let value = match value {
+        Some(value) => Some(parse_config_value(key, kind, value)?),
+        None => None,
+    };
+    update_config(cfg_path, |cfg_map| {
+        let mut parts = key.split('.').collect::<Vec<&str>>();
+        let last = parts.pop().unwrap();
+        let mut parent = &mut *cfg_map;
+        for part in parts {
+            let child = parent
+                .entry(part)
+                .or_insert_with(|| Value::Object(Map::new()));
+            if !child.is_object() {
+                *child = Value::Object(Map::new());
+            }
+            parent = child.as_object_mut().unwrap();
+        }
+        match value {
+            Some(value) => parent.insert(last.into(), value),
+            None => parent.remove(last),
+        };
+
+        // make sure salt can still read the config before writing it
+        serde_json::from_value::<SaltConfig>(Value::Object(cfg_map.clone())).map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("invalid value for {}: {}", key, e),
+            )
+        })?;
+        Ok(())
+    })
+}
+
+fn config_key_kind(key: &str) -> Result<&'static str> {
+    for (k, kind, _) in CONFIG_KEYS {
+        if k == key {
+            return Ok(kind);
+        }
+    }
+    let keys = CONFIG_KEYS.iter().map(|k| k.0).collect::<Vec<&str>>();
+    Err(std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        format!(
+            "unknown config key {}, available keys: {}",
+            key,
+            keys.join(", ")
+        ),
+    ))
+}
+
+fn parse_config_value(key: &str, kind: &str, value: &str) -> Result<Value> {
+    let parsed = match kind {
+        "number" => value.parse::<u64>().ok().map(Value::from),
+        // `key=value` pairs separated by commas
+        "map" => value
+            .split(',')
+            .map(|v| v.trim())
+            .filter(|v| !v.is_empty())
+            .map(|v| {
+                v.split_once('=')
+                    .map(|(k, v)| (k.trim().to_owned(), Value::from(v.trim())))
+            })
+            .collect::<Option<Map<String, Value>>>()
+            .map(Value::Object),
+        _ => Some(Value::from(value)),
+    };
+    parsed.ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("{} expects a {} value, got {}", key, kind, value),
+        )
+    })
+}
+
+/// migrate upgrades a config written by an older version of salt to
+/// [CONFIG_VERSION], returns true if anything was changed
+fn migrate(cfg: &mut Value) -> bool {
+    let cfg_map = match cfg.as_object_mut() {
+        Some(cfg_map) => cfg_map,
+        None => return false,
+    };
+    let version = cfg_map.get("version").and_then(|v| v.as_u64()).unwrap_or(0);
+    if version >= CONFIG_VERSION {
+        return false;
+    }
+
+    // 0 -> 1: configs before versioning could have a null or missing
+    // pinned_paths and an empty editor
+    if version < 1 {
+        if !matches!(cfg_map.get("pinned_paths"), Some(Value::Object(_))) {
+            cfg_map.insert("pinned_paths".into(), Value::Object(Map::new()));
+        }
+        if cfg_map.get("editor") == Some(&Value::String(String::new())) {
+            cfg_map.remove("editor");
+        }
+    }
+
+    cfg_map.insert("version".into(), Value::from(CONFIG_VERSION));
+    true
+}
+
+/// strip_json_comments blanks out `//` and `/* */` comments so configs can be
+/// documented in place, newlines are kept to keep error positions intact
+fn strip_json_comments(json: &str) -> String {
+    let mut stripped = String::with_capacity(json.len());
+    let mut chars = json.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            stripped.push(c);
+            if c == '\\' {
+                if let Some(escaped) = chars.next() {
+                    stripped.push(escaped);
+                }
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                stripped.push(c);
+            }
+            ('/', Some('/')) => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        stripped.push('\n');
+                        break;
+                    }
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut prev = ' ';
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        stripped.push('\n');
+                    }
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+            }
+            _ => stripped.push(c),
+        }
+    }
+    stripped
+}
+
+fn config_error(cfg_path: &Path, cfg_str: &str, e: serde_json::Error) -> std::io::Error {
+    let line = cfg_str
+        .lines()
+        .nth(e.line().saturating_sub(1))
+        .unwrap_or_default()
+        .trim();
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!(
+            "error while reading salt config {}:{}:{} `{}` {}",
+            cfg_path.to_string_lossy(),
+            e.line(),
+            e.column(),
+            line,
+            e
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        get_config_value, load_config, masked_config_value, migrate, set_config_value,
+        strip_json_comments, update_pinned_paths, CONFIG_VERSION, SECRET_MASK,
+    };
+    use crate::app::test_util::TempDir;
+    use crate::app::DocAssets;
+
+    /// temp_config writes a config with content, it is removed along with the
+    /// returned folder
+    fn temp_config(name: &str, content: &str) -> (TempDir, std::path::PathBuf) {
+        let dir = TempDir::new(&format!("config-{}", name));
+        let cfg_path = dir.join(".config");
+        std::fs::write(&cfg_path, content).unwrap();
+        (dir, cfg_path)
+    }
+
+    #[test]
+    fn test_strip_json_comments() {
+        let json = "{\n  \"editor\": \"code\", // changed it\n  /* multi\n line */ \"url\": \"http://a//b\"\n}";
+        let stripped = strip_json_comments(json);
+
+        assert_eq!(stripped.lines().count(), json.lines().count());
+        let value = serde_json::from_str::<serde_json::Value>(&stripped).unwrap();
+        assert_eq!(value["editor"], "code");
+        assert_eq!(value["url"], "http://a//b");
+    }
+
+    #[test]
+    fn test_migrate_unversioned_config() {
+        let mut cfg = serde_json::json!({ "editor": "", "pinned_paths": null });
+
+        assert!(migrate(&mut cfg));
+        assert_eq!(cfg["version"], CONFIG_VERSION);
+        assert!(cfg["pinned_paths"].is_object());
+        assert!(cfg.get("editor").is_none());
+        assert!(!migrate(&mut cfg));
+
+        // an editor already in the config, `vi` included, may have been picked
+        // by hand, so it is kept and still wins over $VISUAL and $EDITOR
+        let mut cfg = serde_json::json!({ "version": 1, "editor": "vi" });
+        assert!(!migrate(&mut cfg));
+        assert_eq!(cfg["editor"], "vi");
+    }
+
+    #[test]
+    fn test_load_tolerant_config() {
+        let (_dir, cfg_path) = temp_config(
+            "tolerant",
+            "{\n  \"editor\": \"code\", // comment\n  \"unknown\": true\n}",
+        );
+        let cfg = load_config(&cfg_path).unwrap();
+
+        assert_eq!(cfg.editor, Some("code".into()));
+        assert_eq!(cfg.version, CONFIG_VERSION);
+        assert!(cfg.pinned_paths.is_empty());
+        // migration is written back and unknown keys are kept
+        let on_disk = std::fs::read_to_string(&cfg_path).unwrap();
+        assert!(on_disk.contains(&format!("\"version\": {}", CONFIG_VERSION)));
+        assert!(on_disk.contains("\"unknown\": true"));
+
+        update_pinned_paths(&cfg_path, |pins| {
+            pins.insert("salt".into(), "/tmp/salt".into());
+        })
+        .unwrap();
+        let cfg = load_config(&cfg_path).unwrap();
+        assert_eq!(cfg.pinned_paths.get("salt"), Some(&"/tmp/salt".into()));
+        assert!(std::fs::read_to_string(&cfg_path)
+            .unwrap()
+            .contains("\"unknown\": true"));
+    }
+
+    #[test]
+    fn test_load_config_error_points_at_line() {
+        let (_dir, cfg_path) = temp_config("invalid", "{\n  \"version\": 1,\n  \"editor\": 5\n}");
+        let err = load_config(&cfg_path).unwrap_err();
+
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains(".config:3:"));
+        assert!(err.to_string().contains("`\"editor\": 5`"));
+    }
+
+    #[test]
+    fn test_get_and_set_config_values() {
+        let (_dir, cfg_path) = temp_config("set", "{ \"version\": 1, \"editor\": \"vi\" }");
+
+        set_config_value(&cfg_path, "editor", Some("code")).unwrap();
+        let cfg = load_config(&cfg_path).unwrap();
+        assert_eq!(get_config_value(&cfg, "editor").unwrap(), "code");
+
+        set_config_value(&cfg_path, "editor", None).unwrap();
+        let cfg = load_config(&cfg_path).unwrap();
+        // unset settings fall back to their defaults
+        assert!(get_config_value(&cfg, "editor").unwrap().is_null());
+
+        set_config_value(&cfg_path, "docs.assets", Some("offline")).unwrap();
+        let cfg = load_config(&cfg_path).unwrap();
+        assert_eq!(cfg.docs.assets, DocAssets::Offline);
+        assert!(set_config_value(&cfg_path, "docs.assets", Some("floppy")).is_err());
+
+        set_config_value(&cfg_path, "docs.css", Some("~/docs.css")).unwrap();
+        let cfg = load_config(&cfg_path).unwrap();
+        assert_eq!(cfg.docs.css.as_deref(), Some("~/docs.css"));
+        assert_eq!(cfg.docs.assets, DocAssets::Offline);
+
+        set_config_value(
+            &cfg_path,
+            "http.tokens",
+            Some("gitlab.corp.io=a, github.com = b"),
+        )
+        .unwrap();
+        set_config_value(&cfg_path, "http.timeout", Some("5")).unwrap();
+        let cfg = load_config(&cfg_path).unwrap();
+        assert_eq!(cfg.http.tokens.get("github.com"), Some(&"b".to_owned()));
+        assert_eq!(cfg.http.tokens.len(), 2);
+        assert_eq!(cfg.http.timeout, Some(5));
+        assert!(set_config_value(&cfg_path, "http.tokens", Some("github.com")).is_err());
+        assert!(set_config_value(&cfg_path, "http.timeout", Some("soon")).is_err());
+
+        let masked = masked_config_value(&cfg, "http.tokens").unwrap();
+        assert_eq!(masked["github.com"], SECRET_MASK);
+        assert_eq!(masked["gitlab.corp.io"], SECRET_MASK);
+        assert_eq!(masked_config_value(&cfg, "http.timeout").unwrap(), 5);
+
+        assert!(set_config_value(&cfg_path, "pinned_paths", Some("x")).is_err());
+        assert!(get_config_value(&cfg, "colour").is_err());
+    }
+}
@@ -0,0 +1,102 @@
+use std::io::Result;
+use std::path::{Path, PathBuf};
+
+use super::log;
+
+/// folders which never contain salt projects of their own and are expensive
+/// to walk through
+const SKIPPED_DIRS: [&str; 3] = ["node_modules", "target", "vendor"];
+
+/// find_project_root walks up from start to the nearest folder containing a
+/// SALT.md file, the same way git looks for the `.git` folder
+pub(crate) fn find_project_root(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .find(|dir| dir.join("SALT.md").is_file())
+        .map(|dir| dir.to_path_buf())
+}
+
+/// scan_projects returns every folder under root (including root itself)
+/// which contains a SALT.md file, sorted by path. Folders below root which
+/// can not be read are skipped
+pub(crate) fn scan_projects(root: &Path) -> Result<Vec<PathBuf>> {
+    let mut found = vec![];
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        if dir.join("SALT.md").is_file() {
+            found.push(dir.clone());
+        }
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if dir != root => {
+                log!("skipping {:?}: {}", dir, e);
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        for entry in entries {
+            let entry = match entry.and_then(|e| e.file_type().map(|t| (e, t))) {
+                Ok((entry, file_type)) if file_type.is_dir() => entry,
+                Ok(_) => continue,
+                Err(e) => {
+                    log!("skipping an entry of {:?}: {}", dir, e);
+                    continue;
+                }
+            };
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if name.starts_with('.') || SKIPPED_DIRS.contains(&name.as_ref()) {
+                continue;
+            }
+            pending.push(entry.path());
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_project_root, scan_projects};
+    use crate::app::test_util::TempDir;
+
+    #[test]
+    fn test_find_and_scan_projects() {
+        let tmp = TempDir::new("discover");
+        let root = tmp.to_path_buf();
+        for dir in [
+            "api/src/bin",
+            "web",
+            "web/node_modules/dep",
+            ".git/x",
+            "docs",
+        ] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for dir in ["", "api", "web", "web/node_modules/dep", ".git/x"] {
+            std::fs::write(root.join(dir).join("SALT.md"), "").unwrap();
+        }
+
+        assert_eq!(
+            find_project_root(&root.join("api/src/bin")),
+            Some(root.join("api"))
+        );
+        assert_eq!(find_project_root(&root.join("docs")), Some(root.clone()));
+        assert_eq!(
+            scan_projects(&root).unwrap(),
+            vec![root.clone(), root.join("api"), root.join("web")]
+        );
+
+        // a folder which can not be read does not stop the scan, root can
+        // still read it when the tests run as root
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let docs = root.join("docs");
+            std::fs::set_permissions(&docs, std::fs::Permissions::from_mode(0o000)).unwrap();
+            assert_eq!(scan_projects(&root).unwrap().len(), 3);
+            std::fs::set_permissions(&docs, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+    }
+}
@@ -1,23 +1,405 @@
 use std::{
-    collections::{hash_map::DefaultHasher, HashMap},
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
     hash::{Hash, Hasher},
+    io::{Read, Result, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
 };
 
-use markdown::{Block, Span};
+use markdown::{Block, ListItem, Span};
 use serde::{Deserialize, Serialize};
 
-use super::ProjectDefinition;
+use super::export::search_entries;
+use super::gfm::{footnote_definition, parse_table, task_marker, Align, Table};
+use super::highlight::{highlight, language_class};
+use super::parser::split_env_block;
+use super::paths::expand_home;
+use super::{log, DocsConfig, ProjectDefinition, VERSION};
 
+static HBS_FILE: &str = include_str!("../../templates/salt.hbs");
+static INDEX_HBS_FILE: &str = include_str!("../../templates/index.hbs");
+static LIVE_RELOAD_HBS_FILE: &str = include_str!("../../templates/partials/live_reload.hbs");
+static SEARCH_HBS_FILE: &str = include_str!("../../templates/partials/search.hbs");
+static SALT_CSS_FILE: &str = include_str!("../../templates/assets/salt.css");
+static SALT_JS_FILE: &str = include_str!("../../templates/assets/salt.js");
+
+static SALT_HBS_NAME: &str = "salt.hbs";
+static INDEX_HBS_NAME: &str = "index.hbs";
+
+/// Doc is what the doc template is rendered with. Custom templates are
+/// written against the names of its fields, so they are kept as they are
 #[derive(Serialize, Deserialize)]
 pub struct Doc {
+    /// version of salt
     version: String,
+    /// name of the project
     project: String,
+    /// script drawing the diagrams of the doc on page load
     script_content: String,
-    // TODO: consider converting this to struct for clearer understanding
-    titles: Vec<(String, String, String)>,
-    contents: Vec<(String, String, String)>,
+    /// the `###` sections of the doc, in the order of SALT.md
+    sections: Vec<DocSection>,
     about: String,
-    commands: Vec<(String, String)>,
+    /// the commands of the project, sorted by name
+    commands: Vec<DocCommand>,
+    /// env set for every command of the project, sorted by name
+    env: Vec<DocEnv>,
+    /// folder the commands run in, unknown for docs from the web
+    dir: Option<String>,
+    /// other names the project is pinned under
+    pub(crate) aliases: Vec<String>,
+    /// live_reload makes the page poll the doc server and reload itself
+    /// when its SALT.md changes
+    pub(crate) live_reload: bool,
+    /// home links back to the index of all projects when the doc is a part
+    /// of a site
+    pub(crate) home: Option<String>,
+    /// nav links to the docs of the other projects of the site
+    pub(crate) nav: Vec<DocLink>,
+    /// offline pages carry their styles and scripts instead of loading them
+    /// from CDNs
+    pub(crate) offline: bool,
+    /// search_index is the json list of what the search box of the page
+    /// searches through, the sections of the doc unless it is a part of a site
+    pub(crate) search_index: String,
+    /// custom_css is the css of the theme, applied after the built in styles
+    custom_css: Option<String>,
+    /// (path, source) of the template of the theme, replacing salt.hbs
+    #[serde(skip)]
+    template: Option<(PathBuf, String)>,
+    /// relative images the doc shows, as written in SALT.md
+    #[serde(skip)]
+    pub(crate) images: Vec<String>,
+}
+
+/// DocSection is a `###` section of a doc
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct DocSection {
+    /// title of the section, as written after `###`
+    pub(crate) title: String,
+    /// anchor of the section, which is the id of its element
+    pub(crate) id: String,
+    /// the section rendered into html
+    pub(crate) html: String,
+    /// active is set on the section shown when the page is opened
+    pub(crate) active: bool,
+}
+
+/// DocCommand is a command of the project a doc is about
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct DocCommand {
+    pub(crate) name: String,
+    pub(crate) about: String,
+    /// the command line which is run, without its env block
+    pub(crate) command: String,
+    /// env set by the `[KEY=value]` block of the command, sorted by name
+    pub(crate) env: Vec<DocEnv>,
+    /// how the command is run from the command line, `s project command`
+    pub(crate) usage: String,
+}
+
+/// DocEnv is an environment variable set for commands
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DocEnv {
+    pub(crate) name: String,
+    pub(crate) value: String,
+}
+
+/// doc_env sorts env by name
+fn doc_env(env: HashMap<String, String>) -> Vec<DocEnv> {
+    let mut env = env
+        .into_iter()
+        .map(|(name, value)| DocEnv { name, value })
+        .collect::<Vec<DocEnv>>();
+    env.sort_by(|a, b| a.name.cmp(&b.name));
+    env
+}
+
+/// project_aliases are the names of the other projects pinned from the folder
+/// of project, sorted
+pub(crate) fn project_aliases<'a>(
+    projects: impl Iterator<Item = &'a ProjectDefinition>,
+    project: &ProjectDefinition,
+) -> Vec<String> {
+    let name = project.qualified_name();
+    let mut aliases = projects
+        .filter(|p| p.project_path == project.project_path && p.qualified_name() != name)
+        .map(|p| p.qualified_name())
+        .collect::<Vec<String>>();
+    aliases.sort();
+    aliases.dedup();
+    aliases
+}
+
+/// DocLink is a link to the doc of a project
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct DocLink {
+    /// qualified name of the project
+    pub(crate) name: String,
+    pub(crate) link: String,
+}
+
+/// IndexEntry is a project listed on the index page
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct IndexEntry {
+    /// qualified name of the project
+    pub(crate) name: String,
+    pub(crate) about: String,
+    pub(crate) link: String,
+}
+
+/// Theme changes the look of docs, with a handlebars template replacing the
+/// built in one or css added to it
+#[derive(Default, Debug)]
+pub(crate) struct Theme {
+    /// (path, source) of the template
+    pub(crate) template: Option<(PathBuf, String)>,
+    pub(crate) css: Option<String>,
+}
+
+impl Theme {
+    /// load reads the template and css set in the options of project, or
+    /// else in the docs config. Options of projects which are not on disk,
+    /// like docs opened from the web, are left out
+    pub(crate) fn load(project: &ProjectDefinition, config: &DocsConfig) -> Result<Theme> {
+        let on_disk = project.project_path.is_absolute();
+        let pick = |option: &Option<String>, setting: &Option<String>| match option {
+            Some(path) if on_disk => Some(project.project_path.join(path)),
+            _ => setting.as_deref().map(expand_home),
+        };
+        let read = |path: PathBuf| {
+            std::fs::read_to_string(&path)
+                .map(|source| (path.clone(), source))
+                .map_err(|e| {
+                    std::io::Error::new(
+                        e.kind(),
+                        format!("cannot read doc theme {}: {}", path.to_string_lossy(), e),
+                    )
+                })
+        };
+        let template = pick(&project.options.template, &config.template)
+            .map(read)
+            .transpose()?;
+        let css = pick(&project.options.css, &config.css)
+            .map(read)
+            .transpose()?
+            .map(|(_, css)| css);
+        Ok(Theme { template, css })
+    }
+}
+
+/// DocIndex is the page listing every project known to salt, linking to
+/// their docs
+#[derive(Serialize)]
+pub(crate) struct DocIndex {
+    version: String,
+    /// every project, sorted by name
+    projects: Vec<IndexEntry>,
+    pub(crate) live_reload: bool,
+    pub(crate) offline: bool,
+}
+
+impl DocIndex {
+    /// new builds the index from the projects, link gives the address of the
+    /// doc of each project
+    pub(crate) fn new<'a>(
+        projects: impl Iterator<Item = &'a ProjectDefinition>,
+        link: impl Fn(&ProjectDefinition) -> String,
+    ) -> Self {
+        let mut projects = projects
+            .map(|p| IndexEntry {
+                name: p.qualified_name(),
+                about: p.about.clone(),
+                link: link(p),
+            })
+            .collect::<Vec<IndexEntry>>();
+        projects.sort();
+        DocIndex {
+            version: VERSION.to_owned(),
+            projects,
+            live_reload: false,
+            offline: false,
+        }
+    }
+
+    pub(crate) fn render_html(&self) -> Result<String> {
+        templates()?
+            .render(INDEX_HBS_NAME, self)
+            .map_err(template_error)
+    }
+}
+
+/// site_nav links to the docs of projects sorted by name, for linking the
+/// docs of a site to each other
+pub(crate) fn site_nav<'a>(
+    projects: impl Iterator<Item = &'a ProjectDefinition>,
+    link: impl Fn(&ProjectDefinition) -> String,
+) -> Vec<DocLink> {
+    let mut nav = projects
+        .map(|p| DocLink {
+            name: p.qualified_name(),
+            link: link(p),
+        })
+        .collect::<Vec<DocLink>>();
+    nav.sort();
+    nav
+}
+
+fn templates() -> Result<handlebars::Handlebars<'static>> {
+    let mut reg = handlebars::Handlebars::new();
+    reg.register_template_string(SALT_HBS_NAME, HBS_FILE)
+        .map_err(template_error)?;
+    reg.register_template_string(INDEX_HBS_NAME, INDEX_HBS_FILE)
+        .map_err(template_error)?;
+    reg.register_partial("live_reload", LIVE_RELOAD_HBS_FILE)
+        .map_err(template_error)?;
+    reg.register_partial("search", SEARCH_HBS_FILE)
+        .map_err(template_error)?;
+    reg.register_partial("salt_css", SALT_CSS_FILE)
+        .map_err(template_error)?;
+    reg.register_partial("salt_js", SALT_JS_FILE)
+        .map_err(template_error)?;
+    Ok(reg)
+}
+
+/// template_error turns a handlebars error into an io error, every template
+/// is built into salt so this points at a bug in salt itself
+fn template_error(err: impl std::fmt::Display) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("error while rendering the doc: {}", err),
+    )
+}
+
+/// theme_error turns a handlebars error in the template of a theme into an
+/// io error
+fn theme_error(path: &Path, err: impl std::fmt::Display) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!(
+            "error in the doc template {}: {}",
+            path.to_string_lossy(),
+            err
+        ),
+    )
+}
+
+/// RESERVED_IDS are ids the doc page uses itself, headings never get them
+const RESERVED_IDS: &[&str] = &[
+    "about",
+    "commands",
+    "search",
+    "search-results",
+    "search-index",
+    "v-pills-tab",
+    "v-pills-tabContent",
+];
+
+/// slugify turns a heading into the readable part of its id, `Set up the
+/// API!` becomes `set-up-the-api`
+pub(crate) fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.trim().chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if (c.is_whitespace() || c == '-' || c == '_') && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_matches('-');
+    if slug.is_empty() {
+        "section".into()
+    } else {
+        slug.to_owned()
+    }
+}
+
+/// Anchors are the ids of the `###` sections of a project and of the `####`
+/// headings inside each section. Ids are slugs of the headings, numbered
+/// when a slug is taken already, so they only change when headings do
+pub(crate) struct Anchors {
+    pub(crate) sections: Vec<String>,
+    /// (heading, id) of the `####` headings of every section
+    pub(crate) headings: Vec<Vec<(String, String)>>,
+}
+
+impl Anchors {
+    pub(crate) fn new(project: &ProjectDefinition) -> Self {
+        let mut used = RESERVED_IDS
+            .iter()
+            .map(|id| id.to_string())
+            .collect::<HashSet<String>>();
+        let mut unique = |text: &str| {
+            let slug = slugify(text);
+            let mut id = slug.clone();
+            let mut n = 1;
+            while !used.insert(id.clone()) {
+                n += 1;
+                id = format!("{}-{}", slug, n);
+            }
+            id
+        };
+        // sections come first so that their ids do not depend on the
+        // headings of the sections before them
+        let sections = project
+            .docs
+            .keys()
+            .map(|title| unique(title))
+            .collect::<Vec<String>>();
+        let headings = project
+            .docs
+            .values()
+            .map(|blocks| {
+                let mut found = vec![];
+                find_headings(blocks, &mut found);
+                found
+                    .into_iter()
+                    .map(|text| {
+                        let id = unique(&text);
+                        (text, id)
+                    })
+                    .collect()
+            })
+            .collect();
+        Anchors { sections, headings }
+    }
+
+    /// section_of finds the title of the section which has the section or
+    /// heading with id
+    pub(crate) fn section_of<'a>(
+        &self,
+        project: &'a ProjectDefinition,
+        id: &str,
+    ) -> Option<&'a String> {
+        let i = self
+            .sections
+            .iter()
+            .zip(&self.headings)
+            .position(|(section, headings)| {
+                section == id || headings.iter().any(|(_, h)| h == id)
+            })?;
+        project.docs.keys().nth(i)
+    }
+}
+
+/// find_headings lists the `####` headings of blocks in the order they are
+/// rendered in
+fn find_headings(blocks: &[Block], found: &mut Vec<String>) {
+    for block in blocks {
+        match block {
+            Block::Header(spans, 4) => found.push(spans_to_text(spans)),
+            Block::Blockquote(blocks) => find_headings(blocks, found),
+            Block::OrderedList(items, _) | Block::UnorderedList(items) => {
+                for item in items {
+                    if let ListItem::Paragraph(blocks) = item {
+                        find_headings(blocks, found);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
 }
 
 fn get_hashed_id<T: Hash>(obj: T) -> u64 {
@@ -26,7 +408,60 @@ fn get_hashed_id<T: Hash>(obj: T) -> u64 {
     hasher.finish()
 }
 
-fn spans_to_html(spans: &Vec<Span>) -> String {
+/// RenderCtx is what rendering a `###` section needs besides its blocks
+struct RenderCtx<'a> {
+    /// position of the section, keeps the ids of elements unique on the page
+    uid: usize,
+    /// address relative images are resolved against
+    image_base: &'a str,
+    /// relative images the section shows, as written in SALT.md
+    images: Vec<String>,
+    /// ids of the footnotes defined in the section, numbered in this order
+    footnotes: Vec<String>,
+    /// (heading, id) of the `####` headings of the section
+    headings: &'a [(String, String)],
+    /// position of the next `####` heading in headings
+    next_heading: usize,
+    script_content: String,
+}
+
+impl RenderCtx<'_> {
+    fn image_src(&mut self, src: &str) -> String {
+        let is_relative = !src.contains("://")
+            && !src.starts_with('/')
+            && !src.starts_with("data:")
+            && !src.starts_with('#');
+        if !is_relative || self.image_base.is_empty() {
+            return src.to_owned();
+        }
+        let relative = src.trim_start_matches("./");
+        if !self.images.iter().any(|i| i == relative) {
+            self.images.push(relative.to_owned());
+        }
+        format!("{}/{}", self.image_base.trim_end_matches('/'), relative)
+    }
+
+    /// text_to_html turns the `[^id]` references to footnotes of the section
+    /// into links
+    fn text_to_html(&self, text: &str) -> String {
+        let mut html = escape_html(text);
+        for (i, id) in self.footnotes.iter().enumerate() {
+            let id = escape_html(id);
+            html = html.replace(
+                &format!("[^{}]", id),
+                &format!(
+                    r##"<sup class="fn-ref"><a href="#fn-{}-{}">{}</a></sup>"##,
+                    self.uid,
+                    id,
+                    i + 1
+                ),
+            );
+        }
+        html
+    }
+}
+
+fn spans_to_html(spans: &[Span], ctx: &mut RenderCtx) -> String {
     let mut html = String::new();
     for span in spans {
         match span {
@@ -35,22 +470,34 @@ fn spans_to_html(spans: &Vec<Span>) -> String {
             }
             markdown::Span::Text(t) => {
                 if !t.starts_with("<!--") {
-                    html.push_str(t);
+                    html.push_str(&ctx.text_to_html(t));
                 }
             }
             markdown::Span::Code(c) => {
-                html.push_str(&format!("<code>{c}</code>"));
+                html.push_str(&format!("<code>{}</code>", escape_html(c)));
             }
             markdown::Span::Link(text, link, _) => {
-                html.push_str(&format!(r#"<a href="{link}" target="_blank">{text}</a>"#));
+                html.push_str(&format!(
+                    r#"<a href="{}" target="_blank" rel="noopener">{}</a>"#,
+                    escape_html(&safe_url(link, LINK_SCHEMES)),
+                    escape_html(text)
+                ));
+            }
+            markdown::Span::Image(alt, src, title) => {
+                let src = ctx.image_src(src);
+                html.push_str(&format!(
+                    r#"<img src="{}" alt="{}" title="{}" />"#,
+                    escape_html(&safe_url(&src, IMAGE_SCHEMES)),
+                    escape_html(alt),
+                    escape_html(title.as_deref().unwrap_or_default())
+                ));
             }
-            markdown::Span::Image(_, _, _) => todo!(),
             markdown::Span::Emphasis(ispans) => {
-                let emph_html = spans_to_html(ispans);
+                let emph_html = spans_to_html(ispans, ctx);
                 html.push_str(&format!("<i>{}</i>", &emph_html));
             }
             markdown::Span::Strong(spans) => {
-                let strong_html = spans_to_html(spans);
+                let strong_html = spans_to_html(spans, ctx);
                 html.push_str(&format!("<b>{}</b>", &strong_html));
             }
         }
@@ -59,21 +506,43 @@ fn spans_to_html(spans: &Vec<Span>) -> String {
     html
 }
 
-fn blocks_to_html(html: &mut String, script_content: &mut String, blocks: &Vec<Block>, uid: usize) {
+fn blocks_to_html(html: &mut String, blocks: &[Block], ctx: &mut RenderCtx) {
     for (i, block) in blocks.iter().enumerate() {
         match block {
             Block::Blockquote(bq) => {
                 html.push_str(r#"<div class="bq">"#);
-                blocks_to_html(html, script_content, bq, uid);
+                blocks_to_html(html, bq, ctx);
                 html.push_str("</div>");
             }
-            Block::Header(h, _) => {
-                html.push_str("<h4>");
-                html.push_str(&spans_to_html(h));
-                html.push_str("</h4>");
+            Block::Header(h, 4) => {
+                // the ids were handed out in the same order by find_headings
+                let id = ctx.headings.get(ctx.next_heading).map(|(_, id)| id.clone());
+                ctx.next_heading += 1;
+                match id {
+                    Some(id) => html.push_str(&format!(
+                        r##"<h4 id="{id}">{}<a class="anchor" href="#{id}">#</a></h4>"##,
+                        spans_to_html(h, ctx)
+                    )),
+                    None => html.push_str(&format!("<h4>{}</h4>", spans_to_html(h, ctx))),
+                }
+            }
+            Block::Header(h, level) => {
+                // `###` is the section itself, deeper headers keep their level
+                let level = (*level).clamp(4, 6);
+                html.push_str(&format!("<h{}>", level));
+                html.push_str(&spans_to_html(h, ctx));
+                html.push_str(&format!("</h{}>", level));
             }
             Block::Paragraph(spans) => {
-                html.push_str(&spans_to_html(spans));
+                if footnote_definition(spans).is_some() {
+                    // footnotes are listed at the end of the section
+                    continue;
+                }
+                if let Some(table) = parse_table(spans) {
+                    table_to_html(html, &table, ctx);
+                    continue;
+                }
+                html.push_str(&spans_to_html(spans, ctx));
                 // peeking if next block is also a paragraph
                 if blocks.get(i + 1).is_some()
                     && matches!(blocks.get(i + 1).unwrap(), Block::Paragraph(_))
@@ -82,134 +551,929 @@ fn blocks_to_html(html: &mut String, script_content: &mut String, blocks: &Vec<B
                 }
             }
             Block::CodeBlock(meta, cblock) => {
-                if meta.is_some() && meta.as_ref().unwrap().eq("dot") {
-                    // uid_cblock is the combination of dot graph value
-                    // and appended by -- and followed by uid
-                    // which is the position of this section
-                    let mut uid_cblock = cblock.to_owned();
-                    uid_cblock.push_str(&format!("--{}--", uid));
-
-                    // create a unique id from the uid_cblock for injecting
-                    // graphviz graph on page load
-                    let viz_element = format!("viz-{}", get_hashed_id(uid_cblock));
-
-                    // create a div with viz element id
-                    html.push_str(&format!("<div id='{}'></div>", viz_element));
-
-                    // add function call to load dot graph into the viz element on window load
-                    append_dot_script_block(&viz_element, script_content, cblock);
-                } else {
-                    html.push_str(&format!("<pre>{cblock}</pre>"));
+                let info = meta.as_deref().unwrap_or_default();
+                match fence_renderer(info) {
+                    Some(render) => html.push_str(&render(cblock, ctx)),
+                    None => code_block_to_html(html, info, cblock),
                 }
             }
             Block::OrderedList(items, _) => {
                 html.push_str("<ol>");
-                // TODO: extract this for loop into a function
-                for item in items {
-                    match item {
-                        markdown::ListItem::Simple(t) => {
-                            html.push_str(&format!("<li>{}</li>", spans_to_html(t)));
-                        }
-                        markdown::ListItem::Paragraph(p) => {
-                            let mut list_para = String::from("<li>");
-                            blocks_to_html(&mut list_para, script_content, p, uid);
-                            html.push_str(&list_para);
-                            html.push_str("</li>");
-                        }
-                    }
-                }
+                list_items_to_html(html, items, ctx);
                 html.push_str("</ol>");
             }
             Block::UnorderedList(items) => {
                 html.push_str("<ul>");
+                list_items_to_html(html, items, ctx);
+                html.push_str("</ul>");
+            }
+            Block::Hr => html.push_str("<hr />"),
+            // html written in SALT.md is shown as text, a doc fetched from
+            // the web must not be able to run scripts
+            Block::Raw(raw) => html.push_str(&escape_html(raw)),
+        }
+    }
+}
+
+fn list_items_to_html(html: &mut String, items: &[ListItem], ctx: &mut RenderCtx) {
+    for item in items {
+        match item {
+            ListItem::Simple(t) => match task_marker(t) {
+                Some((checked, t)) => {
+                    html.push_str(&format!(
+                        r#"<li class="task">{}{}</li>"#,
+                        checkbox_html(checked),
+                        spans_to_html(&t, ctx)
+                    ));
+                }
+                None => html.push_str(&format!("<li>{}</li>", spans_to_html(t, ctx))),
+            },
+            ListItem::Paragraph(p) => {
+                let task = match p.first() {
+                    Some(Block::Paragraph(spans)) => task_marker(spans),
+                    _ => None,
+                };
+                match task {
+                    Some((checked, spans)) => {
+                        html.push_str(r#"<li class="task">"#);
+                        html.push_str(&checkbox_html(checked));
+                        let mut blocks = vec![Block::Paragraph(spans)];
+                        blocks.extend_from_slice(&p[1..]);
+                        blocks_to_html(html, &blocks, ctx);
+                    }
+                    None => {
+                        html.push_str("<li>");
+                        blocks_to_html(html, p, ctx);
+                    }
+                }
+                html.push_str("</li>");
+            }
+        }
+    }
+}
+
+/// code_block_to_html renders a code block highlighted for the language in
+/// its info string, with a button copying the code
+fn code_block_to_html(html: &mut String, info: &str, code: &str) {
+    let class = language_class(info)
+        .map(|c| format!(r#" class="{}""#, c))
+        .unwrap_or_default();
+    let code = highlight(info, code).unwrap_or_else(|| escape_html(code));
+    html.push_str(&format!(
+        concat!(
+            r#"<div class="code-block">"#,
+            r#"<button class="copy" type="button" title="Copy to clipboard" hidden>Copy</button>"#,
+            "<pre{}><code>{}</code></pre></div>"
+        ),
+        class, code
+    ));
+}
+
+fn checkbox_html(checked: bool) -> String {
+    format!(
+        r#"<input type="checkbox" disabled{} /> "#,
+        if checked { " checked" } else { "" }
+    )
+}
+
+fn table_to_html(html: &mut String, table: &Table, ctx: &mut RenderCtx) {
+    let cell_html = |tag: &str, align: &Align, spans: &[Span], ctx: &mut RenderCtx| {
+        let style = align
+            .css()
+            .map(|a| format!(r#" style="text-align: {}""#, a))
+            .unwrap_or_default();
+        format!("<{tag}{style}>{}</{tag}>", spans_to_html(spans, ctx))
+    };
+    html.push_str(r#"<table class="table"><thead><tr>"#);
+    for (cell, align) in table.head.iter().zip(&table.align) {
+        html.push_str(&cell_html("th", align, cell, ctx));
+    }
+    html.push_str("</tr></thead><tbody>");
+    for row in &table.rows {
+        html.push_str("<tr>");
+        for (cell, align) in row.iter().zip(&table.align) {
+            html.push_str(&cell_html("td", align, cell, ctx));
+        }
+        html.push_str("</tr>");
+    }
+    html.push_str("</tbody></table>");
+}
+
+/// toc_to_html lists the `####` headings of a section at its top, when there
+/// are enough of them to need one
+fn toc_to_html(html: &mut String, headings: &[(String, String)]) {
+    if headings.len() < 2 {
+        return;
+    }
+    html.push_str(r#"<nav class="toc"><ul>"#);
+    for (text, id) in headings {
+        html.push_str(&format!(
+            r##"<li><a href="#{}">{}</a></li>"##,
+            id,
+            escape_html(text)
+        ));
+    }
+    html.push_str("</ul></nav>");
+}
+
+/// footnotes_to_html lists the footnotes defined in blocks, in the order they
+/// are numbered in
+fn footnotes_to_html(html: &mut String, blocks: &[Block], ctx: &mut RenderCtx) {
+    let definitions = blocks
+        .iter()
+        .filter_map(|b| match b {
+            Block::Paragraph(spans) => footnote_definition(spans),
+            _ => None,
+        })
+        .collect::<Vec<(String, Vec<Span>)>>();
+    if definitions.is_empty() {
+        return;
+    }
+    html.push_str(r#"<hr /><ol class="footnotes">"#);
+    for (id, spans) in definitions {
+        let note = spans_to_html(&spans, ctx);
+        html.push_str(&format!(
+            r#"<li id="fn-{}-{}">{}</li>"#,
+            ctx.uid,
+            escape_html(&id),
+            note
+        ));
+    }
+    html.push_str("</ol>");
+}
+
+/// blocks_to_text returns the words of blocks without any markup, for
+/// searching through docs
+pub(crate) fn blocks_to_text(blocks: &[Block]) -> String {
+    let mut parts = vec![];
+    for block in blocks {
+        match block {
+            Block::Header(spans, _) | Block::Paragraph(spans) => parts.push(spans_to_text(spans)),
+            Block::Blockquote(blocks) => parts.push(blocks_to_text(blocks)),
+            Block::CodeBlock(_, code) => parts.push(code.to_owned()),
+            Block::OrderedList(items, _) | Block::UnorderedList(items) => {
                 for item in items {
                     match item {
-                        markdown::ListItem::Simple(t) => {
-                            html.push_str(&format!("<li>{}</li>", spans_to_html(t)));
-                        }
-                        markdown::ListItem::Paragraph(p) => {
-                            let mut list_para = String::from("<li>");
-                            blocks_to_html(&mut list_para, script_content, p, uid);
-                            html.push_str(&list_para);
-                            html.push_str("</li>");
-                        }
+                        markdown::ListItem::Simple(spans) => parts.push(spans_to_text(spans)),
+                        markdown::ListItem::Paragraph(blocks) => parts.push(blocks_to_text(blocks)),
                     }
                 }
-                html.push_str("</ul>");
             }
-            _ => continue,
+            Block::Raw(raw) => parts.push(raw.to_owned()),
+            Block::Hr => {}
+        }
+    }
+    parts
+        .join(" ")
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+fn spans_to_text(spans: &[Span]) -> String {
+    let mut text = String::new();
+    for span in spans {
+        match span {
+            Span::Break => text.push(' '),
+            Span::Text(t) if t.starts_with("<!--") => {}
+            Span::Text(t) | Span::Code(t) => text.push_str(t),
+            Span::Link(t, _, _) | Span::Image(t, _, _) => text.push_str(t),
+            Span::Emphasis(spans) | Span::Strong(spans) => text.push_str(&spans_to_text(spans)),
+        }
+    }
+    text
+}
+
+/// FenceRenderer renders a fenced code block as something other than code,
+/// script the result needs on page load goes into the script_content of ctx
+type FenceRenderer = fn(&str, &mut RenderCtx<'_>) -> String;
+
+/// FENCE_RENDERERS are the renderers of fenced code blocks by the language
+/// after their opening fence, blocks in any other language are shown as
+/// highlighted code
+const FENCE_RENDERERS: &[(&str, FenceRenderer)] =
+    &[("dot", dot_to_html), ("mermaid", mermaid_to_html)];
+
+fn fence_renderer(info: &str) -> Option<FenceRenderer> {
+    let lang = info.split_whitespace().next()?;
+    FENCE_RENDERERS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(lang))
+        .map(|(_, render)| *render)
+}
+
+/// DOT_PROGRAM is the graphviz program drawing dot diagrams while docs are
+/// rendered, without it they are drawn in the browser
+const DOT_PROGRAM: &str = "dot";
+
+/// DOT_TIMEOUT is how long DOT_PROGRAM can take to draw a diagram before it
+/// is stopped and the diagram is left to the browser
+const DOT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// DOT_CACHE keeps what DOT_PROGRAM drew for a diagram, so the doc server
+/// does not draw every diagram again on each request
+static DOT_CACHE: OnceLock<Mutex<HashMap<u64, Option<String>>>> = OnceLock::new();
+
+/// DOT_CACHE_SIZE is the number of diagrams DOT_CACHE holds, it is emptied
+/// when full so a long running doc server does not keep every diagram which
+/// was ever edited
+const DOT_CACHE_SIZE: usize = 256;
+
+fn dot_to_html(code: &str, ctx: &mut RenderCtx) -> String {
+    draw_dot(DOT_PROGRAM, code, ctx)
+}
+
+/// draw_dot renders a dot diagram drawn by program, falling back to drawing
+/// it in the browser when program can not draw it
+fn draw_dot(program: &str, code: &str, ctx: &mut RenderCtx) -> String {
+    let key = get_hashed_id((program, code));
+    let cache = DOT_CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+    let cached = cache.lock().ok().and_then(|c| c.get(&key).cloned());
+    let svg = match cached {
+        Some(svg) => svg,
+        None => {
+            let svg = dot_to_svg(program, code, DOT_TIMEOUT);
+            if let Ok(mut cache) = cache.lock() {
+                if cache.len() >= DOT_CACHE_SIZE {
+                    cache.clear();
+                }
+                cache.insert(key, svg.clone());
+            }
+            svg
+        }
+    };
+    match svg {
+        // the svg is shown as an image, which can not run scripts or follow
+        // links a graph may have
+        Some(svg) => format!(
+            r#"<div class="diagram"><img src="data:image/svg+xml,{}" alt="diagram" /></div>"#,
+            percent_encode(&svg)
+        ),
+        None => diagram_to_html("draw_into_element", code, ctx),
+    }
+}
+
+/// dot_to_svg draws a dot diagram with program, returns None if program is
+/// not installed, can not draw the diagram or is not done within timeout
+fn dot_to_svg(program: &str, code: &str, timeout: Duration) -> Option<String> {
+    let mut child = Command::new(program)
+        .arg("-Tsvg")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .ok()?;
+    // the diagram is written and the svg read from other threads, so that
+    // neither side can block the other and salt can keep time
+    let mut stdin = child.stdin.take()?;
+    let input = code.to_owned();
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let mut stdout = child.stdout.take()?;
+    let reader = std::thread::spawn(move || {
+        let mut svg = Vec::new();
+        stdout.read_to_end(&mut svg).map(|_| svg)
+    });
+    let mut stderr = child.stderr.take()?;
+    let errors = std::thread::spawn(move || {
+        let mut errors = String::new();
+        let _ = stderr.read_to_string(&mut errors);
+        errors
+    });
+
+    let started = Instant::now();
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if started.elapsed() < timeout => {
+                std::thread::sleep(Duration::from_millis(10))
+            }
+            _ => {
+                log!("{} took too long to draw the diagram, stopping it", program);
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+        }
+    };
+    let _ = writer.join();
+    let output = reader.join().ok()?.ok()?;
+    let errors = errors.join().unwrap_or_default();
+    if !status.success() {
+        log!("{} can not draw the diagram: {}", program, errors);
+        return None;
+    }
+    let svg = String::from_utf8(output).ok()?;
+    // the xml declaration and doctype before the svg are not needed
+    svg.find("<svg").map(|i| svg[i..].trim_end().to_owned())
+}
+
+/// percent_encode encodes text for a `data:` address
+fn percent_encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for b in text.bytes() {
+        if b.is_ascii_alphanumeric() || b"-_.~/:=;,()!*+@".contains(&b) {
+            encoded.push(b as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", b));
         }
     }
+    encoded
+}
+
+fn mermaid_to_html(code: &str, ctx: &mut RenderCtx) -> String {
+    diagram_to_html("draw_mermaid_into_element", code, ctx)
 }
 
-fn append_dot_script_block(viz_element: &String, script_content: &mut String, cblock: &str) {
-    let dot_block = format!(
-        r#"draw_into_element(`{}`, '{}');
+/// diagram_to_html renders an empty element which the javascript function
+/// draw fills with the diagram described by code on page load
+fn diagram_to_html(draw: &str, code: &str, ctx: &mut RenderCtx) -> String {
+    // the same diagram can be in several sections, the position of the
+    // section keeps the id of the element unique
+    let viz_element = format!("viz-{}", get_hashed_id(format!("{}--{}--", code, ctx.uid)));
+    append_script_call(&mut ctx.script_content, draw, &[code, &viz_element]);
+    format!("<div id='{}'></div>", viz_element)
+}
+
+/// append_script_call adds a call of the javascript function to
+/// script_content, with args passed as strings
+fn append_script_call(script_content: &mut String, function: &str, args: &[&str]) {
+    let args = args
+        .iter()
+        .map(script_json)
+        .collect::<Vec<String>>()
+        .join(", ");
+    script_content.push_str(&format!(
+        r#"{}({});
 
     "#,
-        cblock, viz_element
-    );
-    script_content.push_str(&dot_block);
+        function, args
+    ));
 }
 
-fn get_html(blocks: &Vec<Block>, script_content: &mut String, uid: usize) -> String {
-    let mut html = String::new();
-    blocks_to_html(&mut html, script_content, blocks, uid);
+/// LINK_SCHEMES are the schemes a link in a doc may use, links without a
+/// scheme are relative and always allowed
+const LINK_SCHEMES: &[&str] = &["http", "https", "mailto"];
+
+/// IMAGE_SCHEMES are the schemes an image in a doc may use, `file` is how
+/// relative images of local projects are shown
+const IMAGE_SCHEMES: &[&str] = &["http", "https", "file", "data"];
+
+/// escape_html escapes text for use in html content and quoted attributes
+pub(crate) fn escape_html(text: &str) -> String {
+    let mut html = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            '\'' => html.push_str("&#39;"),
+            c => html.push(c),
+        }
+    }
     html
 }
 
+/// safe_url returns url if it is relative or uses one of schemes, otherwise
+/// `#` so that `javascript:` and the like never end up in a page. Browsers
+/// skip whitespace and control characters in schemes, so they are ignored
+/// here as well
+fn safe_url(url: &str, schemes: &[&str]) -> String {
+    let compact = url
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect::<String>();
+    let scheme = match compact.find([':', '/', '?', '#']) {
+        Some(i) if compact[i..].starts_with(':') => compact[..i].to_ascii_lowercase(),
+        _ => return url.to_owned(),
+    };
+    let allowed = schemes.contains(&scheme.as_str())
+        // only images are allowed as data, which can not run scripts
+        && (scheme != "data" || compact[5..].to_ascii_lowercase().starts_with("image/"));
+    if allowed {
+        url.to_owned()
+    } else {
+        log!("dropping unsafe url: {}", url);
+        "#".to_owned()
+    }
+}
+
+/// script_json encodes value as json which can be put inside a `<script>`
+/// element, every `<` is escaped so that text like `</script>` can not end
+/// the element
+pub(crate) fn script_json(value: &(impl Serialize + ?Sized)) -> String {
+    serde_json::to_string(value)
+        .unwrap_or_default()
+        .replace('<', "\\u003c")
+}
+
+/// get_html renders the blocks of the section at uid, returns its html and
+/// the relative images it shows
+fn get_html(
+    blocks: &[Block],
+    script_content: &mut String,
+    uid: usize,
+    image_base: &str,
+    headings: &[(String, String)],
+) -> (String, Vec<String>) {
+    let mut ctx = RenderCtx {
+        uid,
+        image_base,
+        images: vec![],
+        headings,
+        next_heading: 0,
+        footnotes: blocks
+            .iter()
+            .filter_map(|b| match b {
+                Block::Paragraph(spans) => footnote_definition(spans).map(|(id, _)| id),
+                _ => None,
+            })
+            .collect(),
+        script_content: String::new(),
+    };
+    let mut html = String::new();
+    toc_to_html(&mut html, headings);
+    blocks_to_html(&mut html, blocks, &mut ctx);
+    footnotes_to_html(&mut html, blocks, &mut ctx);
+    script_content.push_str(&ctx.script_content);
+    (html, ctx.images)
+}
+
+impl Doc {
+    /// render_html renders the doc into a html page through salt.hbs, or the
+    /// template of its theme
+    pub(crate) fn render_html(&self) -> Result<String> {
+        let mut reg = templates()?;
+        let path = match &self.template {
+            Some((path, source)) => {
+                reg.register_template_string(SALT_HBS_NAME, source)
+                    .map_err(|e| theme_error(path, e))?;
+                path
+            }
+            None => return reg.render(SALT_HBS_NAME, self).map_err(template_error),
+        };
+        reg.render(SALT_HBS_NAME, self)
+            .map_err(|e| theme_error(path, e))
+    }
+
+    /// activate shows the section titled section when the doc is opened
+    /// instead of the first one, returns false if there is no such section
+    pub(crate) fn activate(&mut self, section: &str) -> bool {
+        let i = match self
+            .sections
+            .iter()
+            .position(|s| s.title.eq_ignore_ascii_case(section.trim()))
+        {
+            Some(i) => i,
+            None => return false,
+        };
+        for (j, section) in self.sections.iter_mut().enumerate() {
+            section.active = i == j;
+        }
+        true
+    }
+
+    /// set_theme renders the doc with the template and css of theme
+    pub(crate) fn set_theme(&mut self, theme: Theme) {
+        self.template = theme.template;
+        // the css is put into a `<style>` element as it is, a `</` in it
+        // could close the element and start markup of its own
+        self.custom_css = theme.css.map(|css| css.replace("</", "<\\/"));
+    }
+}
+
 impl From<ProjectDefinition> for Doc {
     fn from(value: ProjectDefinition) -> Self {
+        // opened from disk relative images are found next to the SALT.md
+        let image_base = match value.project_path.is_absolute() {
+            true => format!("file://{}", value.project_path.to_string_lossy()),
+            false => String::new(),
+        };
+        Doc::with_image_base(value, &image_base)
+    }
+}
+
+impl Doc {
+    /// with_image_base builds the doc of a project whose relative images are
+    /// served from image_base, they are kept as written when it is empty
+    pub(crate) fn with_image_base(value: ProjectDefinition, image_base: &str) -> Self {
+        let search_index = script_json(&search_entries(&value, ""));
+        let anchors = Anchors::new(&value);
+        let qualified_name = value.qualified_name();
         let mut doc = Doc {
             version: value.version.clone(),
             project: value.options.name,
             script_content: String::new(),
-            contents: vec![],
-            titles: vec![],
+            sections: vec![],
             about: value.about,
             commands: vec![],
+            env: doc_env(value.env),
+            dir: match value.exec_path.is_absolute() {
+                true => Some(value.exec_path.to_string_lossy().into()),
+                false => None,
+            },
+            aliases: vec![],
+            live_reload: false,
+            home: None,
+            nav: vec![],
+            offline: false,
+            search_index,
+            custom_css: None,
+            template: None,
+            images: vec![],
         };
 
-        // here we create map of all sections/titles
-        // (which is shown in the left side) of the documentation
-        let mut index_idhash_map: HashMap<usize, String> = HashMap::new();
-        for (i, title) in value.docs.keys().enumerate() {
-            let idhash = get_hashed_id(title.clone()).to_string();
-            index_idhash_map.insert(i, idhash.clone());
-            let data = (
-                title.to_owned(),
-                idhash,
-                if i == 0 { "active".into() } else { "".into() },
-            );
-            doc.titles.push(data);
-        }
-
-        // here we push contents of each section
-        for (i, content) in value.docs.values().enumerate() {
+        // the first section is shown when the page is opened
+        for (i, (title, content)) in value.docs.iter().enumerate() {
             let mut script_chunk = String::new();
-            let html = get_html(content, &mut script_chunk, i);
+            let (html, images) = get_html(
+                content,
+                &mut script_chunk,
+                i,
+                image_base,
+                &anchors.headings[i],
+            );
             doc.script_content.push_str(&script_chunk);
+            for image in images {
+                if !doc.images.contains(&image) {
+                    doc.images.push(image);
+                }
+            }
 
-            // with corresponding:
-            // - html string
-            // - title of each section
-            // - and if we want to show that section active (default active: 0)
-            let data = (
+            doc.sections.push(DocSection {
+                title: title.to_owned(),
+                id: anchors.sections[i].to_owned(),
                 html,
-                index_idhash_map.get(&i).unwrap().to_owned(),
-                if i == 0 {
-                    "show active".into()
-                } else {
-                    "".into()
-                },
-            );
-            doc.contents.push(data);
+                active: i == 0,
+            });
         }
 
-        for (k, v) in value.commands {
-            let data = (k.clone(), v.about.clone());
-            doc.commands.push(data);
+        for (name, command) in value.commands {
+            let (env, line) = split_env_block(&command.command);
+            let usage = format!("s {} {}", qualified_name, name);
+            doc.commands.push(DocCommand {
+                command: line.to_owned(),
+                env: doc_env(env),
+                usage,
+                name,
+                about: command.about,
+            });
         }
+        doc.commands.sort_by(|a, b| a.name.cmp(&b.name));
         doc
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use std::time::Duration;
+
+    use super::{
+        dot_to_svg, draw_dot, percent_encode, project_aliases, slugify, Anchors, Doc, DocEnv,
+        RenderCtx, Theme, DOT_CACHE, DOT_CACHE_SIZE, DOT_TIMEOUT,
+    };
+    use crate::app::test_util::TempDir;
+    use crate::app::{DocsConfig, ProjectDefinition};
+
+    fn render(section: &str) -> String {
+        let md = format!("### Section\n\n{}\n\n## options\n\n- name - api\n", section);
+        let mut project = ProjectDefinition::from(markdown::tokenize(&md));
+        project.project_path = "/work/api".into();
+        let doc = Doc::from(project);
+        doc.sections[0].html.clone()
+    }
+
+    #[test]
+    fn test_render_gfm() {
+        let html = render("| a | b |\n|---|--:|\n| 1 | 2 |");
+        assert_eq!(
+            html,
+            r#"<table class="table"><thead><tr><th>a</th><th style="text-align: right">b</th></tr></thead><tbody><tr><td>1</td><td style="text-align: right">2</td></tr></tbody></table>"#
+        );
+
+        let html = render("- [ ] todo\n- [x] done");
+        assert!(html.contains(r#"<li class="task"><input type="checkbox" disabled /> todo</li>"#));
+        assert!(html.contains("disabled checked /> done"));
+
+        let html = render("before\n\n---\n\n##### small\n\nafter");
+        assert!(html.contains("<hr />"));
+        assert!(html.contains("<h5>small</h5>"));
+        assert!(html.contains("after"));
+
+        let html = render("see this[^1]\n\n[^1]: the note");
+        assert!(html.contains(r##"<sup class="fn-ref"><a href="#fn-0-1">1</a></sup>"##));
+        assert!(html.contains(r#"<li id="fn-0-1">the note</li>"#));
+    }
+
+    #[test]
+    fn test_render_nested_lists() {
+        let html =
+            render("- one\n    - nested `a`\n    - [x] two\n- three\n\n1. first\n    1. inner");
+        assert!(html.contains(
+            r#"<ul><li>one<ul><li>nested <code>a</code></li><li class="task"><input type="checkbox" disabled checked /> two</li></ul></li><li>three</li></ul>"#
+        ));
+        assert!(html.contains("<ol><li>first<ol><li>inner</li></ol></li></ol>"));
+    }
+
+    #[test]
+    fn test_render_code_blocks() {
+        let html = render("```rust,ignore\nlet x = 1;\n```");
+        assert!(html.contains(r#"<pre class="lang-rust"><code><span class="hl-kw">let</span> x = <span class="hl-num">1</span>;</code></pre>"#));
+        assert!(html.contains(r#"<button class="copy""#));
+
+        let html = render("```\nplain <text>\n```");
+        assert!(html.contains("<pre><code>plain &lt;text&gt;</code></pre>"));
+    }
+
+    #[test]
+    fn test_render_diagrams() {
+        let md = "### Graphs\n\n```dot\ndigraph { a -> b }\n```\n\n```mermaid\ngraph TD; A-->B\n```\n\n## options\n\n- name - api\n";
+        let doc = Doc::from(ProjectDefinition::from(markdown::tokenize(md)));
+        let html = &doc.sections[0].html;
+        // dot diagrams are drawn by graphviz when it is installed, see
+        // test_dot_to_svg for both ways
+        assert!(!html.contains("<pre"));
+        assert!(doc
+            .script_content
+            .contains(r#"draw_mermaid_into_element("graph TD; A-->B", "viz-"#));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_dot_to_svg() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new("dot");
+        let fake = |name: &str, script: &str| {
+            let program = dir.join(name);
+            std::fs::write(&program, script).unwrap();
+            std::fs::set_permissions(&program, std::fs::Permissions::from_mode(0o755)).unwrap();
+            program.to_string_lossy().into_owned()
+        };
+        let program = fake(
+            "dot",
+            "#!/bin/sh\ncat > /dev/null\nprintf '<?xml version=\"1.0\"?>\\n<svg><text>a &amp; b</text></svg>\\n'\n",
+        );
+        let slow = fake("slow-dot", "#!/bin/sh\ncat > /dev/null\nexec sleep 5\n");
+
+        let svg = dot_to_svg(&program, "digraph { a -> b }", DOT_TIMEOUT).unwrap();
+        assert_eq!(svg, "<svg><text>a &amp; b</text></svg>");
+        assert_eq!(
+            percent_encode(&svg),
+            "%3Csvg%3E%3Ctext%3Ea%20%26amp;%20b%3C/text%3E%3C/svg%3E"
+        );
+        assert!(dot_to_svg("salt-no-such-program", "digraph {}", DOT_TIMEOUT).is_none());
+        let started = std::time::Instant::now();
+        assert!(dot_to_svg(&slow, "digraph {}", Duration::from_millis(200)).is_none());
+        assert!(started.elapsed() < Duration::from_secs(4));
+
+        let mut ctx = RenderCtx {
+            uid: 1,
+            image_base: "",
+            images: vec![],
+            footnotes: vec![],
+            headings: &[],
+            next_heading: 0,
+            script_content: String::new(),
+        };
+        let html = draw_dot(&program, "digraph { a -> b }", &mut ctx);
+        assert_eq!(
+            html,
+            r#"<div class="diagram"><img src="data:image/svg+xml,%3Csvg%3E%3Ctext%3Ea%20%26amp;%20b%3C/text%3E%3C/svg%3E" alt="diagram" /></div>"#
+        );
+        assert!(ctx.script_content.is_empty());
+        // drawn diagrams are kept, the program is not run again
+        std::fs::remove_file(&program).unwrap();
+        assert_eq!(draw_dot(&program, "digraph { a -> b }", &mut ctx), html);
+
+        // without the program the diagram is drawn in the browser
+        let html = draw_dot("salt-no-such-program", "digraph { a -> b }", &mut ctx);
+        assert!(html.starts_with("<div id='viz-"), "{}", html);
+        assert!(ctx
+            .script_content
+            .contains(r#"draw_into_element("digraph { a -> b }", "viz-"#));
+
+        // the cache does not grow past its size
+        for i in 0..=DOT_CACHE_SIZE {
+            draw_dot(
+                "salt-no-such-program",
+                &format!("digraph {{ a{} }}", i),
+                &mut ctx,
+            );
+        }
+        assert!(DOT_CACHE.get().unwrap().lock().unwrap().len() <= DOT_CACHE_SIZE);
+    }
+
+    #[test]
+    fn test_anchors() {
+        assert_eq!(slugify("Set up the API!"), "set-up-the-api");
+        assert_eq!(slugify("  a -- b_c  "), "a-b-c");
+        assert_eq!(slugify("🧂"), "section");
+
+        let md = "### About\n\n#### Setup\n\n#### Setup\n\n### Setup\n\n> #### Deep\n\n## options\n\n- name - api\n";
+        let project = ProjectDefinition::from(markdown::tokenize(md));
+        let anchors = Anchors::new(&project);
+        // the meta sections of the page keep their ids
+        assert_eq!(anchors.sections, vec!["about-2", "setup"]);
+        assert_eq!(
+            anchors.headings[0],
+            vec![
+                ("Setup".to_owned(), "setup-2".to_owned()),
+                ("Setup".to_owned(), "setup-3".to_owned())
+            ]
+        );
+        assert_eq!(anchors.headings[1][0].1, "deep");
+        assert_eq!(anchors.section_of(&project, "setup-3").unwrap(), "About");
+        assert_eq!(anchors.section_of(&project, "deep").unwrap(), "Setup");
+        assert!(anchors.section_of(&project, "nope").is_none());
+
+        let doc = Doc::from(project);
+        assert_eq!(doc.sections[0].id, "about-2");
+        let html = &doc.sections[0].html;
+        assert!(html.starts_with(r##"<nav class="toc"><ul><li><a href="#setup-2">Setup</a></li>"##));
+        assert!(html
+            .contains(r##"<h4 id="setup-3">Setup<a class="anchor" href="#setup-3">#</a></h4>"##));
+        assert!(doc.sections[1].html.contains(r#"<h4 id="deep">"#));
+        // a single heading needs no table of contents
+        assert!(!doc.sections[1].html.contains("toc"));
+    }
+
+    #[test]
+    fn test_render_images() {
+        let html = render("![logo](img/logo.png \"Logo\") and ![remote](https://x.y/a.png)");
+        assert!(
+            html.contains(r#"<img src="file:///work/api/img/logo.png" alt="logo" title="Logo" />"#)
+        );
+        assert!(html.contains(r#"src="https://x.y/a.png""#));
+    }
+
+    #[test]
+    fn test_render_hostile() {
+        let html = render("<script>alert(1)</script> & `<b>` [x](javascript:alert(1))");
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("&lt;script&gt;alert(1)&lt;/script&gt; &amp; "));
+        assert!(html.contains("<code>&lt;b&gt;</code>"));
+        assert!(html.contains(r##"<a href="#" target="_blank" rel="noopener">x</a>"##));
+
+        for link in [
+            "JavaScript:alert(1)",
+            "java\tscript:alert(1)",
+            "data:text/html,x",
+        ] {
+            let html = render(&format!("[x]({})", link));
+            assert!(html.contains(r##"href="#""##), "{} is dropped", link);
+        }
+        for link in [
+            "https://x.y/?a=1&b=2",
+            "mailto:a@x.y",
+            "docs/setup.md",
+            "#usage",
+        ] {
+            let html = render(&format!("[x]({})", link));
+            assert!(!html.contains(r##"href="#""##), "{} is kept", link);
+        }
+        assert!(render("[x](https://x.y/?a=1&b=2)").contains(r#"href="https://x.y/?a=1&amp;b=2""#));
+
+        let html = render("![a\"onerror=\"x](https://x.y/a.png)");
+        assert!(html.contains(r#"alt="a&quot;onerror=&quot;x""#));
+
+        let html = render("```\n</pre><img src=x onerror=alert(1)>\n```");
+        assert!(
+            html.contains("<pre><code>&lt;/pre&gt;&lt;img src=x onerror=alert(1)&gt;</code></pre>")
+        );
+    }
+
+    #[test]
+    fn test_render_hostile_page() {
+        let md = "### <img src=x onerror=alert(1)>\n\n```dot\ndigraph { a -> b } `); alert(1); (`</script><script>alert(2)\n```\n\n## options\n\n- name - api\n";
+        let page = Doc::from(ProjectDefinition::from(markdown::tokenize(md)))
+            .render_html()
+            .unwrap();
+        assert!(!page.contains("<img src=x"));
+        assert!(page.contains("&lt;img src&#x3D;x onerror&#x3D;alert(1)&gt;"));
+        // the graph is a json string which can not close the script element
+        assert!(page.contains(
+            r#"draw_into_element("digraph { a -> b } `); alert(1); (`\u003c/script>\u003cscript>alert(2)""#
+        ));
+        assert!(!page.contains("</script><script>alert(2)"));
+    }
+
+    #[test]
+    fn test_render_commands() {
+        let md = "## commands\n\n- run - `[RUST_LOG=debug PORT=9000]cargo run` - runs it\n- b - `cargo build` - builds\n\n## env\n\n- PORT - 8080\n\n## options\n\n- name - api\n";
+        let mut project = ProjectDefinition::from(markdown::tokenize(md));
+        project.bundle = Some("platform".into());
+        project.project_path = "/work/api".into();
+        project.exec_path = "/work/api".into();
+        let mut pinned = project.clone();
+        pinned.bundle = None;
+        pinned.options.name = "backend".into();
+        let projects = [project.clone(), pinned];
+        assert_eq!(
+            project_aliases(projects.iter(), &project),
+            vec!["backend".to_owned()]
+        );
+
+        let mut doc = Doc::from(project.clone());
+        doc.aliases = project_aliases(projects.iter(), &project);
+        let names = doc
+            .commands
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(names, vec!["b", "run"]);
+        let run = &doc.commands[1];
+        assert_eq!(run.command, "cargo run");
+        assert_eq!(run.usage, "s platform/api run");
+        assert_eq!(
+            run.env,
+            vec![
+                DocEnv {
+                    name: "PORT".into(),
+                    value: "9000".into()
+                },
+                DocEnv {
+                    name: "RUST_LOG".into(),
+                    value: "debug".into()
+                },
+            ]
+        );
+
+        let page = doc.render_html().unwrap();
+        assert!(page.contains("<pre><code>s platform/api run</code></pre>"));
+        assert!(page.contains("Runs <code>cargo run</code>"));
+        assert!(page.contains("<td><code>RUST_LOG</code></td><td><code>debug</code></td>"));
+        assert!(page.contains("<td><code>PORT</code></td><td><code>8080</code></td>"));
+        assert!(page.contains("Commands run in <code>/work/api</code>"));
+        assert!(page.contains("Also pinned as <code>backend</code>"));
+
+        // docs from the web do not know where they would run
+        project.exec_path = "".into();
+        project.env = HashMap::new();
+        let page = Doc::from(project).render_html().unwrap();
+        assert!(!page.contains("Commands run in"));
+        assert!(!page.contains("Every command runs with"));
+    }
+
+    #[test]
+    fn test_theme() {
+        let dir = TempDir::new("theme");
+        std::fs::create_dir_all(dir.join("docs")).unwrap();
+        std::fs::write(
+            dir.join("docs/doc.hbs"),
+            "{{project}}:{{#each sections}}[{{title}}#{{id}}{{#if active}}*{{/if}}]{{/each}}{{#each commands}}({{name}}){{/each}}",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("user.css"),
+            "h4 { color: red; }\n/* </style><script>alert(1)</script> */",
+        )
+        .unwrap();
+        let md = "### Setup\n\nrun it\n\n### Deploy\n\nship it\n\n## commands\n\n- build - `make` - builds\n\n## options\n\n- name - api\n- template - docs/doc.hbs\n";
+        let mut project = ProjectDefinition::from(markdown::tokenize(md));
+        project.project_path = dir.to_path_buf();
+        let config = DocsConfig {
+            css: Some(dir.join("user.css").to_string_lossy().into()),
+            ..Default::default()
+        };
+
+        let mut doc = Doc::from(project.clone());
+        doc.set_theme(Theme::load(&project, &config).unwrap());
+        doc.activate("deploy");
+        assert_eq!(
+            doc.render_html().unwrap(),
+            "api:[Setup#setup][Deploy#deploy*](build)"
+        );
+
+        // the built in template with the css of the config
+        project.options.template = None;
+        let mut doc = Doc::from(project.clone());
+        doc.set_theme(Theme::load(&project, &config).unwrap());
+        let html = doc.render_html().unwrap();
+        assert!(html.contains("h4 { color: red; }"));
+        // the css can not close its style element
+        assert!(html.contains("/* <\\/style><script>alert(1)<\\/script> */"));
+        assert!(!html.contains("</style><script>"));
+
+        // options of docs from the web do not read local files
+        project.project_path = "".into();
+        project.options.css = Some("user.css".into());
+        assert!(Theme::load(&project, &DocsConfig::default())
+            .unwrap()
+            .css
+            .is_none());
+
+        std::fs::write(dir.join("docs/doc.hbs"), "{{#each sections}}").unwrap();
+        project.project_path = dir.to_path_buf();
+        project.options.template = Some("docs/doc.hbs".into());
+        let mut doc = Doc::from(project.clone());
+        doc.set_theme(Theme::load(&project, &config).unwrap());
+        let err = doc.render_html().unwrap_err().to_string();
+        assert!(err.contains("docs/doc.hbs"), "{}", err);
+
+        project.options.template = Some("missing.hbs".into());
+        assert!(Theme::load(&project, &config).is_err());
+    }
+}
@@ -0,0 +1,169 @@
+use std::path::Path;
+use std::process::Command as ProcessCommand;
+
+/// LINE_ARGS are the argument templates of well known editors for opening a
+/// file at a line, used when the configured editor has no template of its own
+const LINE_ARGS: [(&str, &str); 17] = [
+    ("vi", "+{line} {file}"),
+    ("vim", "+{line} {file}"),
+    ("nvim", "+{line} {file}"),
+    ("nano", "+{line} {file}"),
+    ("emacs", "+{line} {file}"),
+    ("emacsclient", "+{line} {file}"),
+    ("kak", "+{line} {file}"),
+    ("code", "-g {file}:{line}"),
+    ("code-insiders", "-g {file}:{line}"),
+    ("codium", "-g {file}:{line}"),
+    ("cursor", "-g {file}:{line}"),
+    ("subl", "{file}:{line}"),
+    ("zed", "{file}:{line}"),
+    ("hx", "{file}:{line}"),
+    ("micro", "{file}:{line}"),
+    ("idea", "--line {line} {file}"),
+    ("goland", "--line {line} {file}"),
+];
+
+/// resolve_editor picks the editor to use, the one set in salt config wins
+/// over `$VISUAL` which wins over `$EDITOR`, and `vi` is used if none is set
+pub(crate) fn resolve_editor(configured: Option<&str>) -> String {
+    resolve_editor_with(configured, |key| std::env::var(key).ok())
+}
+
+fn resolve_editor_with(configured: Option<&str>, env: impl Fn(&str) -> Option<String>) -> String {
+    configured
+        .map(|e| e.to_owned())
+        .or_else(|| env("VISUAL"))
+        .or_else(|| env("EDITOR"))
+        .map(|e| e.trim().to_owned())
+        .filter(|e| !e.is_empty())
+        .unwrap_or("vi".into())
+}
+
+/// editor_command builds the command which opens path in editor, at line if
+/// it is given.
+///
+/// The editor can be a template like `code -g {file}:{line}`, where `{file}`,
+/// `{line}` and `{dir}` are replaced before running it. An editor without any
+/// placeholder gets the path appended, and for well known editors the line too.
+pub(crate) fn editor_command(editor: &str, path: &Path, line: Option<usize>) -> ProcessCommand {
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().unwrap_or("vi");
+    let mut args = parts.map(|p| p.to_owned()).collect::<Vec<String>>();
+
+    let has_placeholder = args
+        .iter()
+        .any(|a| a.contains("{file}") || a.contains("{line}") || a.contains("{dir}"));
+    if !has_placeholder {
+        let template = match line {
+            Some(_) => line_args(program).unwrap_or("{file}"),
+            None => "{file}",
+        };
+        args.extend(template.split_whitespace().map(|a| a.to_owned()));
+    }
+
+    let file = path.to_string_lossy();
+    let dir = match path.is_dir() {
+        true => path,
+        false => path.parent().unwrap_or(path),
+    };
+    let mut cmd = ProcessCommand::new(program);
+    for arg in args {
+        cmd.arg(
+            arg.replace("{file}", &file)
+                .replace("{line}", &line.unwrap_or(1).to_string())
+                .replace("{dir}", &dir.to_string_lossy()),
+        );
+    }
+    cmd
+}
+
+fn line_args(program: &str) -> Option<&'static str> {
+    let name = Path::new(program).file_name()?.to_str()?;
+    LINE_ARGS
+        .iter()
+        .find(|(editor, _)| *editor == name)
+        .map(|(_, args)| *args)
+}
+
+/// find_command_line returns the line number (starting at 1) where command
+/// is defined inside the commands section of a SALT.md file
+pub(crate) fn find_command_line(md: &str, command: &str) -> Option<usize> {
+    let mut in_commands = false;
+    for (i, line) in md.lines().enumerate() {
+        let line = line.trim();
+        if let Some(header) = line.strip_prefix("## ") {
+            in_commands = matches!(
+                header.trim().to_lowercase().as_str(),
+                "commands" | "command"
+            );
+            continue;
+        }
+        if !in_commands {
+            continue;
+        }
+        let item = match line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")) {
+            Some(item) => item,
+            None => continue,
+        };
+        if item.split(" - ").next().map(|k| k.trim()) == Some(command) {
+            return Some(i + 1);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{editor_command, find_command_line, resolve_editor_with};
+
+    fn args(cmd: &std::process::Command) -> Vec<String> {
+        cmd.get_args()
+            .map(|a| a.to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_resolve_editor() {
+        let env = |key: &str| match key {
+            "VISUAL" => Some("code -w".to_owned()),
+            "EDITOR" => Some("nano".to_owned()),
+            _ => None,
+        };
+        assert_eq!(resolve_editor_with(Some("hx"), env), "hx");
+        assert_eq!(resolve_editor_with(None, env), "code -w");
+        assert_eq!(
+            resolve_editor_with(None, |k| (k == "EDITOR").then(|| "nano".to_owned())),
+            "nano"
+        );
+        assert_eq!(resolve_editor_with(None, |_| None), "vi");
+    }
+
+    #[test]
+    fn test_editor_command() {
+        let file = Path::new("/work/api/SALT.md");
+
+        let cmd = editor_command("code -g {file}:{line}", file, Some(12));
+        assert_eq!(cmd.get_program(), "code");
+        assert_eq!(args(&cmd), vec!["-g", "/work/api/SALT.md:12"]);
+
+        let cmd = editor_command("/usr/bin/nvim", file, Some(3));
+        assert_eq!(args(&cmd), vec!["+3", "/work/api/SALT.md"]);
+
+        let cmd = editor_command("subl -n", file, None);
+        assert_eq!(args(&cmd), vec!["-n", "/work/api/SALT.md"]);
+
+        let cmd = editor_command("my-editor", file, Some(3));
+        assert_eq!(args(&cmd), vec!["/work/api/SALT.md"]);
+    }
+
+    #[test]
+    fn test_find_command_line() {
+        let md = "## about\n\n- b - not a command\n\n## commands\n\n- b - `cargo build` - builds\n- r - `cargo run` - runs\n\n## options\n\n- r - nope\n";
+
+        assert_eq!(find_command_line(md, "b"), Some(7));
+        assert_eq!(find_command_line(md, "r"), Some(8));
+        assert_eq!(find_command_line(md, "x"), None);
+    }
+}
@@ -0,0 +1,256 @@
+use std::io::Result;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::doc::{
+    blocks_to_text, project_aliases, script_json, site_nav, Anchors, Doc, DocIndex, Theme,
+};
+use super::parser::DEFAULT_HELP;
+use super::{log, Command, DocAssets, DocsConfig, ProjectDefinition, ProjectMap};
+
+/// SEARCH_INDEX_FILE is the file of an exported site listing the text of
+/// every doc section, for searching the site without a server
+pub(crate) static SEARCH_INDEX_FILE: &str = "search-index.json";
+
+/// SearchEntry is a `###` section of a project doc in the search index
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct SearchEntry {
+    pub(crate) project: String,
+    pub(crate) section: String,
+    /// link to the section, relative to the root of the site
+    pub(crate) link: String,
+    /// text of the section without any markup
+    pub(crate) text: String,
+}
+
+/// page_name is the file the doc of project is exported to, bundle members
+/// are named `bundle--project.html` to keep the site flat
+pub(crate) fn page_name(project: &ProjectDefinition) -> String {
+    format!("{}.html", project.qualified_name().replace('/', "--"))
+}
+
+/// search_entries lists the about, help, commands and doc sections of
+/// project for the search index, link is the address of the project page
+pub(crate) fn search_entries(project: &ProjectDefinition, link: &str) -> Vec<SearchEntry> {
+    let entry = |section: String, anchor: &str, text: String| SearchEntry {
+        project: project.qualified_name(),
+        section,
+        link: format!("{}#{}", link, anchor),
+        text,
+    };
+    let mut entries = vec![];
+    if !project.about.is_empty() {
+        entries.push(entry("About".into(), "about", project.about.clone()));
+    }
+    if !project.help.is_empty() && project.help != DEFAULT_HELP {
+        entries.push(entry("Help".into(), "about", project.help.clone()));
+    }
+    let mut commands = project
+        .commands
+        .iter()
+        .collect::<Vec<(&String, &Command)>>();
+    commands.sort_by_key(|(name, _)| *name);
+    for (name, command) in commands {
+        let section = format!("command {}", name);
+        entries.push(entry(section, "commands", command.about.clone()));
+    }
+    let anchors = Anchors::new(project);
+    for ((title, blocks), id) in project.docs.iter().zip(&anchors.sections) {
+        entries.push(entry(title.to_owned(), id, blocks_to_text(blocks)));
+    }
+    entries
+}
+
+/// site_search_entries lists the search entries of every project sorted by
+/// name, link gives the address of the doc of each project
+pub(crate) fn site_search_entries(
+    project_map: &ProjectMap,
+    link: impl Fn(&ProjectDefinition) -> String,
+) -> Vec<SearchEntry> {
+    let mut projects = project_map.values().collect::<Vec<&ProjectDefinition>>();
+    projects.sort_by_key(|p| p.qualified_name());
+    projects
+        .into_iter()
+        .flat_map(|p| search_entries(p, &link(p)))
+        .collect()
+}
+
+/// export_site writes the docs of every project into out_dir as a static site
+/// with an index page, a page per project and a search index, returns the
+/// number of projects exported
+pub(crate) fn export_site(
+    project_map: &ProjectMap,
+    out_dir: &Path,
+    config: &DocsConfig,
+) -> Result<usize> {
+    std::fs::create_dir_all(out_dir)?;
+    let link = |p: &ProjectDefinition| page_name(p);
+
+    let offline = config.assets == DocAssets::Offline;
+    let mut index = DocIndex::new(project_map.values(), link);
+    index.offline = offline;
+    std::fs::write(out_dir.join("index.html"), index.render_html()?)?;
+
+    let nav = site_nav(project_map.values(), link);
+    let mut names = project_map.keys().collect::<Vec<&String>>();
+    names.sort();
+    // the search box of every page searches the whole site
+    let search_index = site_search_entries(project_map, link);
+    let page_search_index = script_json(&search_index);
+    for name in names {
+        let project = &project_map[name];
+        let page = page_name(project);
+        log!("exporting {} to {}", name, &page);
+
+        // relative images are copied next to the page, under files/{page}
+        let files_dir = format!("files/{}", page.trim_end_matches(".html"));
+        let mut doc = Doc::with_image_base(project.to_owned(), &files_dir);
+        doc.home = Some("index.html".into());
+        doc.nav = nav.clone();
+        doc.aliases = project_aliases(project_map.values(), project);
+        doc.offline = offline;
+        doc.search_index = page_search_index.clone();
+        doc.set_theme(Theme::load(project, config)?);
+        std::fs::write(out_dir.join(&page), doc.render_html()?)?;
+        copy_images(
+            &doc.images,
+            &project.project_path,
+            &out_dir.join(&files_dir),
+        )?;
+    }
+
+    let search_json = serde_json::to_string(&search_index).map_err(|e| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("cannot write the search index: {}", e),
+        )
+    })?;
+    std::fs::write(out_dir.join(SEARCH_INDEX_FILE), search_json)?;
+    Ok(project_map.len())
+}
+
+/// is_inside_project is true for relative paths which can not point outside of
+/// the project folder they are joined to
+pub(crate) fn is_inside_project(path: &Path) -> bool {
+    path.components()
+        .all(|c| matches!(c, std::path::Component::Normal(_)))
+}
+
+/// copy_images copies the relative images of a project into files_dir, an
+/// image which is missing or outside of the project folder is left out
+fn copy_images(images: &[String], project_path: &Path, files_dir: &Path) -> Result<()> {
+    for image in images {
+        let relative = Path::new(image);
+        if !is_inside_project(relative) {
+            log!("skipping image outside of the project: {}", image);
+            continue;
+        }
+        let source = project_path.join(relative);
+        if !source.is_file() {
+            log!("skipping missing image: {:?}", &source);
+            continue;
+        }
+        let target = files_dir.join(relative);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::copy(source, target)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{export_site, search_entries, SearchEntry, SEARCH_INDEX_FILE};
+    use crate::app::test_util::TempDir;
+    use crate::app::{DocAssets, DocsConfig, ProjectDefinition};
+
+    fn project(md: &str, bundle: Option<&str>) -> ProjectDefinition {
+        let mut def = ProjectDefinition::from(markdown::tokenize(md));
+        def.bundle = bundle.map(|b| b.to_owned());
+        def
+    }
+
+    #[test]
+    fn test_search_entries() {
+        let p = project(
+            "## about\n\nthe api\n\n## commands\n\n- rotate - `./rotate.sh` - rotate the keys\n\n## help\n\nask the platform team\n\n## options\n\n- name - api\n",
+            None,
+        );
+        let sections = search_entries(&p, "api.html")
+            .into_iter()
+            .map(|e| (e.section, e.link, e.text))
+            .collect::<Vec<(String, String, String)>>();
+        assert_eq!(sections[1].0, "Help");
+        assert_eq!(sections[1].2, "ask the platform team");
+        assert_eq!(
+            sections[2],
+            (
+                "command rotate".into(),
+                "api.html#commands".into(),
+                "rotate the keys".into()
+            )
+        );
+
+        // projects without help leave it out
+        let p = project("## options\n\n- name - web\n", None);
+        assert!(search_entries(&p, "web.html").is_empty());
+    }
+
+    #[test]
+    fn test_export_site() {
+        let tmp = TempDir::new("export");
+        let out = tmp.join("site");
+        let project_dir = tmp.join("project");
+        std::fs::create_dir_all(&project_dir).unwrap();
+        std::fs::write(project_dir.join("logo.png"), "png").unwrap();
+        let mut map = HashMap::new();
+        for p in [
+            project(
+                "## about\n\nthe api\n\n### Setup\n\nRun `make` first\n\n![logo](./logo.png)\n\n## options\n\n- name - api\n",
+                Some("platform"),
+            ),
+            project("### Usage\n\n- one\n- two\n\n## options\n\n- name - web\n", None),
+        ] {
+            let mut p = p;
+            p.project_path = project_dir.clone();
+            map.insert(p.qualified_name(), p);
+        }
+
+        let config = DocsConfig {
+            assets: DocAssets::Offline,
+            ..Default::default()
+        };
+        assert_eq!(export_site(&map, &out, &config).unwrap(), 2);
+
+        let index = std::fs::read_to_string(out.join("index.html")).unwrap();
+        assert!(index.contains(r#"href="platform--api.html""#));
+        assert!(index.contains(r#"href="web.html""#));
+
+        let api = std::fs::read_to_string(out.join("platform--api.html")).unwrap();
+        assert!(api.contains(r#"href="index.html""#));
+        assert!(api.contains(r#"href="web.html""#));
+        assert!(api.contains(r#"src="files/platform--api/logo.png""#));
+        // the search box of every page searches the whole site
+        assert!(api.contains(r#""link":"web.html#"#));
+        assert_eq!(
+            std::fs::read(out.join("files/platform--api/logo.png")).unwrap(),
+            b"png"
+        );
+        // offline sites load nothing from the internet
+        assert!(!api.contains("https://cdn"));
+        assert!(api.contains("function draw_into_element"));
+
+        let search_index: Vec<SearchEntry> =
+            serde_json::from_str(&std::fs::read_to_string(out.join(SEARCH_INDEX_FILE)).unwrap())
+                .unwrap();
+        assert_eq!(search_index.len(), 3);
+        assert_eq!(search_index[1].section, "Setup");
+        assert_eq!(search_index[1].text, "Run make first logo");
+        assert!(search_index[1].link.starts_with("platform--api.html#"));
+        assert_eq!(search_index[2].text, "one two");
+    }
+}
@@ -0,0 +1,404 @@
+// markdown 0.3 only knows the original markdown syntax, the github flavoured
+// parts salt docs use (tables, task lists, footnotes and rules) come out of it
+// as plain paragraphs, lists or broken emphasis. These helpers find them in
+// what the crate does give back.
+
+use markdown::{Block, Span};
+
+/// Table is a github flavoured table, every row has a cell for each column
+pub(crate) struct Table {
+    pub(crate) align: Vec<Align>,
+    pub(crate) head: Vec<Vec<Span>>,
+    pub(crate) rows: Vec<Vec<Vec<Span>>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Align {
+    None,
+    Left,
+    Center,
+    Right,
+}
+
+impl Align {
+    pub(crate) fn css(&self) -> Option<&'static str> {
+        match self {
+            Align::None => None,
+            Align::Left => Some("left"),
+            Align::Center => Some("center"),
+            Align::Right => Some("right"),
+        }
+    }
+}
+
+/// is_thematic_break is true for a `---`, `***`, `___` or `- - -` rule, which
+/// markdown 0.3 turns into an empty header, emphasis or list
+pub(crate) fn is_thematic_break(block: &Block) -> bool {
+    match block {
+        Block::Hr => true,
+        Block::Header(spans, 2) => spans.is_empty(),
+        Block::UnorderedList(items) => items.is_empty(),
+        Block::Paragraph(spans) => match spans.as_slice() {
+            [Span::Emphasis(inner)] => {
+                matches!(inner.as_slice(), [Span::Text(t)] if t == "*" || t == "_")
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// pipe_tables adds the leading and trailing `|` to the rows of tables
+/// written without them, markdown 0.3 would otherwise read rows like `1 | a`
+/// or `- | -` as lists. Tables start after a blank line and end at the next
+/// one, code blocks are left as they are
+pub(crate) fn pipe_tables(md: &str) -> String {
+    let lines = md.lines().collect::<Vec<&str>>();
+    let mut out = String::with_capacity(md.len());
+    let mut fence: Option<&str> = None;
+    let mut in_table = false;
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+        } else if let Some(marker) = ["```", "~~~"].into_iter().find(|m| trimmed.starts_with(m)) {
+            fence = Some(marker);
+            in_table = false;
+        } else if trimmed.is_empty() {
+            in_table = false;
+        } else if !in_table && !line.starts_with("    ") {
+            let after_blank = i == 0 || lines[i - 1].trim().is_empty();
+            in_table = after_blank
+                && lines.get(i + 1).is_some_and(|next| {
+                    let cells = delimiter_cells(next);
+                    !cells.is_empty() && cells.len() == row_cells(trimmed).len()
+                });
+        }
+        if in_table && fence.is_none() {
+            out.push_str(&piped_row(trimmed));
+        } else {
+            out.push_str(line);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// piped_row makes sure a row of a table starts and ends with `|`
+fn piped_row(row: &str) -> String {
+    let start = if row.starts_with('|') { "" } else { "| " };
+    let end = if row.ends_with('|') && !row.ends_with("\\|") {
+        ""
+    } else {
+        " |"
+    };
+    format!("{}{}{}", start, row, end)
+}
+
+/// row_cells splits a table row at the `|` outside of code spans, without
+/// the cells the leading and trailing pipes leave
+fn row_cells(row: &str) -> Vec<&str> {
+    let mut cells = vec![];
+    let mut start = 0;
+    let mut in_code = false;
+    let mut escaped = false;
+    for (i, c) in row.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '`' => in_code = !in_code,
+            '|' if !in_code => {
+                cells.push(&row[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    cells.push(&row[start..]);
+    if cells.first().is_some_and(|c| c.trim().is_empty()) {
+        cells.remove(0);
+    }
+    if cells.len() > 1 && cells.last().is_some_and(|c| c.trim().is_empty()) {
+        cells.pop();
+    }
+    cells
+}
+
+/// delimiter_cells are the cells of a `:--- | ---:` row, empty when line is
+/// not one
+fn delimiter_cells(line: &str) -> Vec<&str> {
+    if line.starts_with("    ") {
+        return vec![];
+    }
+    let line = line.trim();
+    let cells = row_cells(line);
+    let is_delimiter = |cell: &&str| {
+        let dashes = cell.trim().trim_start_matches(':').trim_end_matches(':');
+        !dashes.is_empty() && dashes.chars().all(|c| c == '-')
+    };
+    // a single `---` without pipes is a rule or a heading underline
+    if !line.contains('|') || !cells.iter().all(is_delimiter) {
+        return vec![];
+    }
+    cells
+}
+
+/// parse_table reads a table out of the spans of a paragraph. Only tables
+/// whose rows start and end with `|` are found, as markdown 0.3 joins the
+/// lines of a paragraph with plain spaces, see [pipe_tables] for the others
+pub(crate) fn parse_table(spans: &[Span]) -> Option<Table> {
+    match spans.first() {
+        Some(Span::Text(t)) if t.starts_with('|') => {}
+        _ => return None,
+    }
+    let mut lines = split_rows(spans).into_iter().map(split_cells);
+    let head = lines.next()?;
+    let align = lines
+        .next()?
+        .iter()
+        .map(|cell| delimiter_align(cell))
+        .collect::<Option<Vec<Align>>>()?;
+    if head.len() != align.len() {
+        return None;
+    }
+    let rows = lines
+        .map(|mut row| {
+            // rows are padded or cut to the number of columns
+            row.resize_with(align.len(), Vec::new);
+            row
+        })
+        .collect();
+    Some(Table { align, head, rows })
+}
+
+/// split_rows splits at the spaces markdown 0.3 puts in place of the line
+/// breaks between rows, which sit between a `|` ending a line and one
+/// starting the next
+fn split_rows(spans: &[Span]) -> Vec<&[Span]> {
+    let mut rows = vec![];
+    let mut start = 0;
+    for i in 1..spans.len().saturating_sub(1) {
+        let is_break = matches!(&spans[i], Span::Text(t) if t == " ");
+        let ends_row = matches!(&spans[i - 1], Span::Text(t) if t.trim_end().ends_with('|'));
+        let starts_row = matches!(&spans[i + 1], Span::Text(t) if t.trim_start().starts_with('|'));
+        if is_break && ends_row && starts_row {
+            rows.push(&spans[start..i]);
+            start = i + 1;
+        }
+    }
+    rows.push(&spans[start..]);
+    rows
+}
+
+/// split_cells splits a row at the `|` in its text, pipes inside code spans
+/// are kept
+fn split_cells(row: &[Span]) -> Vec<Vec<Span>> {
+    let mut cells = vec![];
+    let mut cell: Vec<Span> = vec![];
+    for span in row {
+        let text = match span {
+            Span::Text(t) => t,
+            other => {
+                cell.push(other.clone());
+                continue;
+            }
+        };
+        let mut parts = text.split('|');
+        if let Some(first) = parts.next() {
+            if !first.is_empty() {
+                cell.push(Span::Text(first.to_owned()));
+            }
+        }
+        for part in parts {
+            cells.push(std::mem::take(&mut cell));
+            if !part.is_empty() {
+                cell.push(Span::Text(part.to_owned()));
+            }
+        }
+    }
+    cells.push(cell);
+
+    // the leading and trailing pipes leave an empty cell on each side
+    if cells.first().is_some_and(|c| is_blank(c)) {
+        cells.remove(0);
+    }
+    if cells.last().is_some_and(|c| is_blank(c)) {
+        cells.pop();
+    }
+    cells.iter_mut().for_each(trim_cell);
+    cells
+}
+
+fn is_blank(cell: &[Span]) -> bool {
+    cell.iter()
+        .all(|s| matches!(s, Span::Text(t) if t.trim().is_empty()))
+}
+
+fn trim_cell(cell: &mut Vec<Span>) {
+    if let Some(Span::Text(t)) = cell.first_mut() {
+        *t = t.trim_start().to_owned();
+    }
+    if let Some(Span::Text(t)) = cell.last_mut() {
+        *t = t.trim_end().to_owned();
+    }
+    cell.retain(|s| !matches!(s, Span::Text(t) if t.is_empty()));
+}
+
+/// delimiter_align reads the alignment from a cell of the `|:---|` row
+fn delimiter_align(cell: &[Span]) -> Option<Align> {
+    let text = match cell {
+        [Span::Text(t)] => t.trim(),
+        _ => return None,
+    };
+    let dashes = text.trim_start_matches(':').trim_end_matches(':');
+    if dashes.is_empty() || !dashes.chars().all(|c| c == '-') {
+        return None;
+    }
+    Some(match (text.starts_with(':'), text.ends_with(':')) {
+        (true, true) => Align::Center,
+        (true, false) => Align::Left,
+        (false, true) => Align::Right,
+        (false, false) => Align::None,
+    })
+}
+
+/// task_marker finds the `[ ]` or `[x]` a task list item starts with, returns
+/// whether it is checked and the item without the marker
+pub(crate) fn task_marker(spans: &[Span]) -> Option<(bool, Vec<Span>)> {
+    let (first, rest) = match spans.split_first() {
+        Some((Span::Text(first), rest)) => (first, rest),
+        _ => return None,
+    };
+    let (checked, text) = if let Some(text) = first.strip_prefix("[ ]") {
+        (false, text)
+    } else if let Some(text) = first
+        .strip_prefix("[x]")
+        .or_else(|| first.strip_prefix("[X]"))
+    {
+        (true, text)
+    } else {
+        return None;
+    };
+    if !text.is_empty() && !text.starts_with(' ') {
+        return None;
+    }
+    let mut spans = vec![Span::Text(text.trim_start().to_owned())];
+    spans.extend_from_slice(rest);
+    Some((checked, spans))
+}
+
+/// footnote_definition finds a `[^id]: text` paragraph, returns the id and
+/// the text of the footnote
+pub(crate) fn footnote_definition(spans: &[Span]) -> Option<(String, Vec<Span>)> {
+    let (first, rest) = match spans.split_first() {
+        Some((Span::Text(first), rest)) => (first, rest),
+        _ => return None,
+    };
+    let (id, text) = first.strip_prefix("[^")?.split_once("]:")?;
+    if id.is_empty() || id.contains(char::is_whitespace) {
+        return None;
+    }
+    let mut spans = vec![Span::Text(text.trim_start().to_owned())];
+    spans.extend_from_slice(rest);
+    Some((id.to_owned(), spans))
+}
+
+#[cfg(test)]
+mod tests {
+    use markdown::{Block, ListItem, Span};
+
+    use super::{
+        footnote_definition, is_thematic_break, parse_table, pipe_tables, task_marker, Align,
+    };
+
+    fn first_block(md: &str) -> Block {
+        markdown::tokenize(md).remove(0)
+    }
+
+    fn paragraph(md: &str) -> Vec<Span> {
+        match first_block(md) {
+            Block::Paragraph(spans) => spans,
+            other => panic!("not a paragraph: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_table() {
+        let table =
+            parse_table(&paragraph("| a | b | c |\n|:--|:-:|--:|\n| 1 | `x|y` |\n")).unwrap();
+
+        assert_eq!(table.align, vec![Align::Left, Align::Center, Align::Right]);
+        assert_eq!(table.head[1], vec![Span::Text("b".into())]);
+        assert_eq!(table.rows.len(), 1);
+        assert_eq!(table.rows[0][1], vec![Span::Code("x|y".into())]);
+        // missing cells are filled in
+        assert!(table.rows[0][2].is_empty());
+
+        assert!(parse_table(&paragraph("| not | a table |\n| really |\n")).is_none());
+        assert!(parse_table(&paragraph("just text | with a pipe\n")).is_none());
+    }
+
+    #[test]
+    fn test_pipe_tables() {
+        let md = "a | `b|c`\n:-- | --:\n1 | *x*\n- | y \\|\n\nafter | text\n";
+        assert_eq!(
+            pipe_tables(md),
+            "| a | `b|c` |\n| :-- | --: |\n| 1 | *x* |\n| - | y \\| |\n\nafter | text\n"
+        );
+        let table = parse_table(&paragraph(&pipe_tables(md))).unwrap();
+        assert_eq!(table.align, vec![Align::Left, Align::Right]);
+        assert_eq!(table.rows.len(), 2);
+        assert_eq!(
+            table.rows[0][1],
+            vec![Span::Emphasis(vec![Span::Text("x".into())])]
+        );
+
+        // tables with pipes, rules, code and text with a pipe are kept as they are
+        for md in [
+            "| a | b |\n|---|---|\n| 1 | 2 |\n",
+            "text\n\n---\n",
+            "```\na | b\n--|--\n```\n",
+            "    a | b\n    --|--\n",
+            "text\na | b\n--|--\n",
+            "a | b\n--|--|--\n",
+        ] {
+            assert_eq!(pipe_tables(md), md);
+        }
+    }
+
+    #[test]
+    fn test_task_marker() {
+        let items = match first_block("- [ ] todo\n- [x] done\n- [link](x)\n") {
+            Block::UnorderedList(items) => items,
+            other => panic!("not a list: {:?}", other),
+        };
+        let spans = |item: &ListItem| match item {
+            ListItem::Simple(spans) => spans.clone(),
+            ListItem::Paragraph(_) => panic!("not a simple item"),
+        };
+
+        assert_eq!(
+            task_marker(&spans(&items[0])),
+            Some((false, vec![Span::Text("todo".into())]))
+        );
+        assert!(task_marker(&spans(&items[1])).unwrap().0);
+        assert!(task_marker(&spans(&items[2])).is_none());
+    }
+
+    #[test]
+    fn test_footnotes_and_rules() {
+        assert_eq!(
+            footnote_definition(&paragraph("[^note]: see here\n")),
+            Some(("note".into(), vec![Span::Text("see here".into())]))
+        );
+        assert!(footnote_definition(&paragraph("[link]: nope\n")).is_none());
+
+        for rule in ["---", "***", "___", "- - -"] {
+            let blocks = markdown::tokenize(&format!("text\n\n{}\n", rule));
+            assert!(is_thematic_break(&blocks[1]), "{} is a rule", rule);
+        }
+        assert!(!is_thematic_break(&first_block("*emphasis*\n")));
+    }
+}
@@ -0,0 +1,438 @@
+// Code blocks in docs are highlighted while the page is rendered, so they
+// read well without loading any script. The highlighter only knows words,
+// strings, comments and numbers, which is enough for the snippets docs have.
+
+use super::doc::escape_html;
+
+/// Keys is how a language writes the keys of its maps, which are highlighted
+/// apart from other words
+#[derive(PartialEq)]
+enum Keys {
+    None,
+    /// `key:` at the start of a line, as in yaml
+    Colon,
+    /// `key =` at the start of a line and `[table]` headers, as in toml
+    Equals,
+    /// a string followed by `:`, as in json
+    Quoted,
+}
+
+/// Language is what the highlighter knows about a language
+struct Language {
+    /// names of the language in the info string of a code block, the first
+    /// one is the class of the highlighted block
+    names: &'static [&'static str],
+    keywords: &'static [&'static str],
+    line_comment: Option<&'static str>,
+    block_comment: Option<(&'static str, &'static str)>,
+    /// characters starting and ending a string
+    quotes: &'static str,
+    keys: Keys,
+    /// highlights `$NAME` and `${NAME}`
+    variables: bool,
+}
+
+/// LITERALS are highlighted in every language
+const LITERALS: &[&str] = &[
+    "true",
+    "false",
+    "null",
+    "nil",
+    "None",
+    "True",
+    "False",
+    "undefined",
+];
+
+const LANGUAGES: &[Language] = &[
+    Language {
+        names: &["rust", "rs"],
+        keywords: &[
+            "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+            "extern", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+            "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait",
+            "type", "unsafe", "use", "where", "while",
+        ],
+        line_comment: Some("//"),
+        block_comment: Some(("/*", "*/")),
+        // a `'` is more often a lifetime than a char
+        quotes: "\"",
+        keys: Keys::None,
+        variables: false,
+    },
+    Language {
+        names: &["go", "golang"],
+        keywords: &[
+            "break",
+            "case",
+            "chan",
+            "const",
+            "continue",
+            "default",
+            "defer",
+            "else",
+            "fallthrough",
+            "for",
+            "func",
+            "go",
+            "goto",
+            "if",
+            "import",
+            "interface",
+            "map",
+            "package",
+            "range",
+            "return",
+            "select",
+            "struct",
+            "switch",
+            "type",
+            "var",
+        ],
+        line_comment: Some("//"),
+        block_comment: Some(("/*", "*/")),
+        quotes: "\"'`",
+        keys: Keys::None,
+        variables: false,
+    },
+    Language {
+        names: &["sh", "bash", "shell", "zsh", "console"],
+        keywords: &[
+            "if", "then", "else", "elif", "fi", "for", "while", "until", "do", "done", "case",
+            "esac", "function", "in", "return", "export", "local", "echo", "cd", "exit", "source",
+        ],
+        line_comment: Some("#"),
+        block_comment: None,
+        quotes: "\"'",
+        keys: Keys::None,
+        variables: true,
+    },
+    Language {
+        names: &["python", "py"],
+        keywords: &[
+            "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+            "elif", "else", "except", "finally", "for", "from", "global", "if", "import", "in",
+            "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+            "with", "yield",
+        ],
+        line_comment: Some("#"),
+        block_comment: None,
+        quotes: "\"'",
+        keys: Keys::None,
+        variables: false,
+    },
+    Language {
+        names: &["javascript", "js", "typescript", "ts", "jsx", "tsx"],
+        keywords: &[
+            "async",
+            "await",
+            "break",
+            "case",
+            "catch",
+            "class",
+            "const",
+            "continue",
+            "default",
+            "delete",
+            "do",
+            "else",
+            "export",
+            "extends",
+            "finally",
+            "for",
+            "function",
+            "if",
+            "import",
+            "in",
+            "instanceof",
+            "interface",
+            "let",
+            "new",
+            "of",
+            "return",
+            "static",
+            "super",
+            "switch",
+            "this",
+            "throw",
+            "try",
+            "type",
+            "typeof",
+            "var",
+            "void",
+            "while",
+            "yield",
+        ],
+        line_comment: Some("//"),
+        block_comment: Some(("/*", "*/")),
+        quotes: "\"'`",
+        keys: Keys::None,
+        variables: false,
+    },
+    Language {
+        names: &["json"],
+        keywords: &[],
+        line_comment: None,
+        block_comment: None,
+        quotes: "\"",
+        keys: Keys::Quoted,
+        variables: false,
+    },
+    Language {
+        names: &["yaml", "yml"],
+        keywords: &[],
+        line_comment: Some("#"),
+        block_comment: None,
+        quotes: "\"'",
+        keys: Keys::Colon,
+        variables: false,
+    },
+    Language {
+        names: &["toml"],
+        keywords: &[],
+        line_comment: Some("#"),
+        block_comment: None,
+        quotes: "\"'",
+        keys: Keys::Equals,
+        variables: false,
+    },
+];
+
+/// language finds the language of a code block from its info string, only
+/// the first word counts so `rust,ignore` and `sh title` work as well
+fn language(info: &str) -> Option<&'static Language> {
+    let name = info
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .next()?
+        .to_ascii_lowercase();
+    LANGUAGES.iter().find(|l| l.names.contains(&name.as_str()))
+}
+
+/// language_class is the css class of a code block in the language named by
+/// info, if salt knows that language
+pub(crate) fn language_class(info: &str) -> Option<String> {
+    language(info).map(|l| format!("lang-{}", l.names[0]))
+}
+
+/// highlight turns code into escaped html with its parts wrapped in `hl-*`
+/// spans, returns None for languages salt does not know
+pub(crate) fn highlight(info: &str, code: &str) -> Option<String> {
+    let lang = language(info)?;
+    let chars = code.chars().collect::<Vec<char>>();
+    let mut html = String::with_capacity(code.len());
+    let mut i = 0;
+    let mut line_start = true;
+    while i < chars.len() {
+        if line_start {
+            line_start = false;
+            let indent = scan(&chars, i, |c| c != '\n' && c.is_whitespace());
+            push(&mut html, None, &chars[i..indent]);
+            i = indent;
+            if let Some((start, end)) = key_at(&chars, i, &lang.keys) {
+                push(&mut html, None, &chars[i..start]);
+                push(&mut html, Some("hl-key"), &chars[start..end]);
+                i = end;
+            }
+            continue;
+        }
+
+        let c = chars[i];
+        if c == '\n' {
+            html.push('\n');
+            line_start = true;
+            i += 1;
+        } else if let Some(end) = comment_at(&chars, i, lang) {
+            push(&mut html, Some("hl-com"), &chars[i..end]);
+            i = end;
+        } else if lang.quotes.contains(c) {
+            let end = string_end(&chars, i);
+            let is_key = lang.keys == Keys::Quoted
+                && chars[end..].iter().find(|c| !c.is_whitespace()) == Some(&':');
+            let class = if is_key { "hl-key" } else { "hl-str" };
+            push(&mut html, Some(class), &chars[i..end]);
+            i = end;
+        } else if c.is_ascii_digit() && (i == 0 || !is_word(chars[i - 1])) {
+            let end = scan(&chars, i, |c| {
+                c.is_ascii_alphanumeric() || c == '.' || c == '_'
+            });
+            push(&mut html, Some("hl-num"), &chars[i..end]);
+            i = end;
+        } else if lang.variables && c == '$' && i + 1 < chars.len() {
+            let end = match chars[i + 1] {
+                '{' => (scan(&chars, i + 1, |c| c != '}' && c != '\n') + 1).min(chars.len()),
+                c if is_word(c) => scan(&chars, i + 1, is_word),
+                // `$?`, `$@` and friends
+                _ => i + 2,
+            };
+            push(&mut html, Some("hl-var"), &chars[i..end]);
+            i = end;
+        } else if is_word(c) {
+            let end = scan(&chars, i, is_word);
+            let word = chars[i..end].iter().collect::<String>();
+            let class = if lang.keywords.contains(&word.as_str()) {
+                Some("hl-kw")
+            } else if LITERALS.contains(&word.as_str()) {
+                Some("hl-lit")
+            } else if matches!(chars.get(end), Some('(') | Some('!')) {
+                Some("hl-fn")
+            } else {
+                None
+            };
+            push(&mut html, class, &chars[i..end]);
+            i = end;
+        } else {
+            push(&mut html, None, &chars[i..i + 1]);
+            i += 1;
+        }
+    }
+    Some(html)
+}
+
+fn push(html: &mut String, class: Option<&str>, chars: &[char]) {
+    if chars.is_empty() {
+        return;
+    }
+    let text = escape_html(&chars.iter().collect::<String>());
+    match class {
+        Some(class) => html.push_str(&format!(r#"<span class="{}">{}</span>"#, class, text)),
+        None => html.push_str(&text),
+    }
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// scan returns the position of the first char from start which is not
+/// matched by f
+fn scan(chars: &[char], start: usize, f: impl Fn(char) -> bool) -> usize {
+    chars[start..]
+        .iter()
+        .position(|c| !f(*c))
+        .map_or(chars.len(), |p| start + p)
+}
+
+fn starts_with(chars: &[char], at: usize, s: &str) -> bool {
+    (at..).zip(s.chars()).all(|(i, c)| chars.get(i) == Some(&c))
+}
+
+/// comment_at returns the end of a comment starting at i. A `#` only starts
+/// a comment after a space, as in `${#list}` or `a#b` it does not
+fn comment_at(chars: &[char], i: usize, lang: &Language) -> Option<usize> {
+    if let Some(prefix) = lang.line_comment {
+        let after_space = prefix != "#" || i == 0 || chars[i - 1].is_whitespace();
+        if after_space && starts_with(chars, i, prefix) {
+            return Some(scan(chars, i, |c| c != '\n'));
+        }
+    }
+    if let Some((open, close)) = lang.block_comment {
+        if starts_with(chars, i, open) {
+            let body = i + open.chars().count();
+            let end = (body..chars.len())
+                .find(|j| starts_with(chars, *j, close))
+                .map_or(chars.len(), |j| j + close.chars().count());
+            return Some(end);
+        }
+    }
+    None
+}
+
+/// string_end returns the position after the quote closing the string which
+/// starts at i, or the end of the code if it is never closed
+fn string_end(chars: &[char], i: usize) -> usize {
+    let quote = chars[i];
+    let mut j = i + 1;
+    while j < chars.len() && chars[j] != quote {
+        j += if chars[j] == '\\' { 2 } else { 1 };
+    }
+    (j + 1).min(chars.len())
+}
+
+/// key_at finds the key of a map entry on the line starting at i, returns
+/// where the key starts and ends
+fn key_at(chars: &[char], i: usize, keys: &Keys) -> Option<(usize, usize)> {
+    let line_end = scan(chars, i, |c| c != '\n');
+    let line = &chars[i..line_end];
+    match keys {
+        Keys::Colon => {
+            // entries of a list are `- key: value`
+            let start = if line.starts_with(&['-', ' ']) {
+                i + scan(line, 1, |c| c == ' ')
+            } else {
+                i
+            };
+            let end = scan(chars, start, |c| !":#\"'\n".contains(c));
+            let ends_key = chars.get(end) == Some(&':')
+                && chars.get(end + 1).is_none_or(|c| c.is_whitespace());
+            (ends_key && end > start).then_some((start, end))
+        }
+        Keys::Equals => {
+            if line.first() == Some(&'[') {
+                let end = line.iter().position(|c| *c == ']')?;
+                return Some((i, i + end + 1));
+            }
+            let eq = scan(chars, i, |c| !"=#\"'\n".contains(c));
+            if chars.get(eq) != Some(&'=') {
+                return None;
+            }
+            let end = (i..eq).rev().find(|j| !chars[*j].is_whitespace())? + 1;
+            Some((i, end))
+        }
+        Keys::None | Keys::Quoted => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{highlight, language_class};
+
+    #[test]
+    fn test_highlight() {
+        assert_eq!(
+            highlight("rust", "fn main() { let s = \"<a>\"; } // done").unwrap(),
+            concat!(
+                r#"<span class="hl-kw">fn</span> <span class="hl-fn">main</span>() { "#,
+                r#"<span class="hl-kw">let</span> s = <span class="hl-str">&quot;&lt;a&gt;&quot;</span>; } "#,
+                r#"<span class="hl-com">// done</span>"#
+            )
+        );
+        assert_eq!(
+            highlight("sh title", "echo ${HOME} $1 # home").unwrap(),
+            concat!(
+                r#"<span class="hl-kw">echo</span> <span class="hl-var">${HOME}</span> "#,
+                r#"<span class="hl-var">$1</span> <span class="hl-com"># home</span>"#
+            )
+        );
+        assert_eq!(
+            highlight("json", r#"{"port": 80, "tls": false}"#).unwrap(),
+            concat!(
+                r#"{<span class="hl-key">&quot;port&quot;</span>: <span class="hl-num">80</span>, "#,
+                r#"<span class="hl-key">&quot;tls&quot;</span>: <span class="hl-lit">false</span>}"#
+            )
+        );
+        assert!(highlight("brainfuck", "+[>+<-]").is_none());
+        assert_eq!(language_class("RS,ignore").unwrap(), "lang-rust");
+    }
+
+    #[test]
+    fn test_highlight_keys() {
+        assert_eq!(
+            highlight("yaml", "name: api\n- port: 80 # http\nurl: http://x").unwrap(),
+            concat!(
+                r#"<span class="hl-key">name</span>: api"#,
+                "\n",
+                r#"- <span class="hl-key">port</span>: <span class="hl-num">80</span> <span class="hl-com"># http</span>"#,
+                "\n",
+                r#"<span class="hl-key">url</span>: http://x"#
+            )
+        );
+        assert_eq!(
+            highlight("toml", "[package]\nname = 'salt'").unwrap(),
+            concat!(
+                r#"<span class="hl-key">[package]</span>"#,
+                "\n",
+                r#"<span class="hl-key">name</span> = <span class="hl-str">&#39;salt&#39;</span>"#
+            )
+        );
+    }
+}
@@ -0,0 +1,322 @@
+use std::collections::HashMap;
+use std::io::Result;
+use std::time::Duration;
+
+use reqwest::blocking::{Client, RequestBuilder};
+use reqwest::StatusCode;
+
+use super::paths::expand_home;
+use super::{HttpConfig, VERSION};
+
+/// DEFAULT_TIMEOUT_SECS is how long a request can take when `http.timeout`
+/// is not set
+pub(crate) const DEFAULT_TIMEOUT_SECS: u64 = 30;
+
+/// HttpClient makes the requests of salt, with the timeout, proxy,
+/// certificates and tokens of the http config
+pub(crate) struct HttpClient {
+    client: Client,
+    timeout: u64,
+    tokens: HashMap<String, String>,
+}
+
+impl HttpClient {
+    pub(crate) fn new(config: &HttpConfig) -> Result<HttpClient> {
+        let timeout = config.timeout.unwrap_or(DEFAULT_TIMEOUT_SECS);
+        let mut builder = Client::builder()
+            .timeout(Duration::from_secs(timeout))
+            .user_agent(format!("salt/{}", VERSION));
+        if let Some(proxy) = &config.proxy {
+            let proxy = reqwest::Proxy::all(proxy).map_err(|e| setting_error("http.proxy", e))?;
+            builder = builder.proxy(proxy);
+        }
+        if let Some(path) = &config.ca_bundle {
+            let path = expand_home(path);
+            let pem = std::fs::read_to_string(&path).map_err(|e| {
+                std::io::Error::new(
+                    e.kind(),
+                    format!(
+                        "cannot read http.ca_bundle {}: {}",
+                        path.to_string_lossy(),
+                        e
+                    ),
+                )
+            })?;
+            let certificates = pem_certificates(&pem);
+            if certificates.is_empty() {
+                return Err(setting_error(
+                    "http.ca_bundle",
+                    format!("no certificates in {}", path.to_string_lossy()),
+                ));
+            }
+            for certificate in certificates {
+                let certificate = reqwest::Certificate::from_pem(certificate.as_bytes())
+                    .map_err(|e| setting_error("http.ca_bundle", e))?;
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+        let client = builder.build().map_err(|e| setting_error("http", e))?;
+        Ok(HttpClient {
+            client,
+            timeout,
+            tokens: config.tokens.clone(),
+        })
+    }
+
+    /// get_text downloads the text at url. Failed requests and responses
+    /// which are not a success are errors, with a kind matching the status.
+    /// Tokens are only sent over https, where they can not be read on the way
+    pub(crate) fn get_text(&self, url: &str) -> Result<String> {
+        let resp = self
+            .request(url)
+            .send()
+            .map_err(|e| self.fetch_error(url, e))?;
+        let status = resp.status();
+        if !status.is_success() {
+            let kind = match status {
+                StatusCode::NOT_FOUND => std::io::ErrorKind::NotFound,
+                StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                    std::io::ErrorKind::PermissionDenied
+                }
+                _ => std::io::ErrorKind::Other,
+            };
+            return Err(std::io::Error::new(
+                kind,
+                format!("cannot fetch {}: the server answered {}", url, status),
+            ));
+        }
+        resp.text().map_err(|e| self.fetch_error(url, e))
+    }
+
+    /// request builds a GET of url, with the token of its host as bearer auth
+    /// when url is https
+    fn request(&self, url: &str) -> RequestBuilder {
+        let request = self.client.get(url);
+        match reqwest::Url::parse(url)
+            .ok()
+            .filter(|u| u.scheme() == "https")
+            .and_then(|u| u.host_str().and_then(|host| self.token_for(host)))
+        {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    /// token_for finds the token of host, the token of `example.com` is sent
+    /// to its subdomains as well. The most specific host wins
+    fn token_for(&self, host: &str) -> Option<&str> {
+        self.tokens
+            .iter()
+            .filter(|(h, _)| host == h.as_str() || host.ends_with(&format!(".{}", h)))
+            .max_by_key(|(h, _)| h.len())
+            .map(|(_, token)| token.as_str())
+    }
+
+    fn fetch_error(&self, url: &str, err: reqwest::Error) -> std::io::Error {
+        if err.is_timeout() {
+            return std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                format!(
+                    "cannot fetch {}: no answer in {} seconds, change it with `s config set http.timeout`",
+                    url, self.timeout
+                ),
+            );
+        }
+        std::io::Error::other(format!("cannot fetch {}: {}", url, err))
+    }
+}
+
+/// pem_certificates splits a pem bundle into its certificates
+fn pem_certificates(pem: &str) -> Vec<&str> {
+    const END: &str = "-----END CERTIFICATE-----";
+    pem.match_indices("-----BEGIN CERTIFICATE-----")
+        .filter_map(|(start, _)| {
+            pem[start..]
+                .find(END)
+                .map(|end| &pem[start..start + end + END.len()])
+        })
+        .collect()
+}
+
+fn setting_error(key: &str, err: impl std::fmt::Display) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        format!("invalid {} setting: {}", key, err),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    use super::{pem_certificates, HttpClient};
+    use crate::app::HttpConfig;
+
+    /// stub answers one request with response, and returns the request
+    fn stub(response: &'static str) -> (String, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let n = stream.read(&mut buf).unwrap();
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+            }
+            stream.write_all(response.as_bytes()).unwrap();
+            String::from_utf8(request).unwrap()
+        });
+        (format!("http://{}", addr), handle)
+    }
+
+    fn client(config: HttpConfig) -> HttpClient {
+        HttpClient::new(&config).unwrap()
+    }
+
+    #[test]
+    fn test_get_text() {
+        let (base, handle) =
+            stub("HTTP/1.1 200 OK\r\nContent-Length: 9\r\nConnection: close\r\n\r\n## about\n");
+        let mut config = HttpConfig::default();
+        config.tokens.insert("127.0.0.1".into(), "secret".into());
+        config.tokens.insert("example.com".into(), "other".into());
+        let text = client(config)
+            .get_text(&format!("{}/SALT.md", base))
+            .unwrap();
+        assert_eq!(text, "## about\n");
+        let request = handle.join().unwrap().to_lowercase();
+        assert!(request.starts_with("get /salt.md http/1.1"));
+        // the stub speaks plain http, which tokens are never sent over
+        assert!(!request.contains("authorization"));
+        assert!(request.contains("user-agent: salt/"));
+    }
+
+    #[test]
+    fn test_bearer_token() {
+        let mut config = HttpConfig::default();
+        config.tokens.insert("corp.io".into(), "corp".into());
+        config.tokens.insert("git.corp.io".into(), "git".into());
+        let client = client(config);
+        let auth = |url: &str| {
+            let request = client.request(url).build().unwrap();
+            request
+                .headers()
+                .get("authorization")
+                .map(|v| v.to_str().unwrap().to_owned())
+        };
+        // the most specific host wins, subdomains get the token of their host
+        assert_eq!(
+            auth("https://git.corp.io/SALT.md").as_deref(),
+            Some("Bearer git")
+        );
+        assert_eq!(
+            auth("https://raw.git.corp.io/SALT.md").as_deref(),
+            Some("Bearer git")
+        );
+        assert_eq!(
+            auth("https://docs.corp.io/SALT.md").as_deref(),
+            Some("Bearer corp")
+        );
+        assert_eq!(auth("https://notcorp.io/SALT.md"), None);
+        assert_eq!(auth("http://git.corp.io/SALT.md"), None);
+    }
+
+    #[test]
+    fn test_get_text_errors() {
+        for (response, kind, status) in [
+            (
+                "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                std::io::ErrorKind::NotFound,
+                "404 Not Found",
+            ),
+            (
+                "HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                std::io::ErrorKind::PermissionDenied,
+                "403 Forbidden",
+            ),
+            (
+                "HTTP/1.1 502 Bad Gateway\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                std::io::ErrorKind::Other,
+                "502 Bad Gateway",
+            ),
+        ] {
+            let (base, handle) = stub(response);
+            let err = client(HttpConfig::default())
+                .get_text(&format!("{}/SALT.md", base))
+                .unwrap_err();
+            handle.join().unwrap();
+            assert_eq!(err.kind(), kind);
+            assert!(err.to_string().contains(status), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_get_text_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/SALT.md", listener.local_addr().unwrap());
+        let config = HttpConfig {
+            timeout: Some(1),
+            ..Default::default()
+        };
+        let err = client(config).get_text(&url).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+        drop(listener);
+    }
+
+    #[test]
+    fn test_proxy() {
+        let (base, handle) =
+            stub("HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nhi");
+        let config = HttpConfig {
+            proxy: Some(base),
+            ..Default::default()
+        };
+        let text = client(config)
+            .get_text("http://docs.salt.invalid/SALT.md")
+            .unwrap();
+        assert_eq!(text, "hi");
+        let request = handle.join().unwrap();
+        assert!(request.starts_with("GET http://docs.salt.invalid/SALT.md HTTP/1.1"));
+    }
+
+    #[test]
+    fn test_invalid_settings() {
+        let config = HttpConfig {
+            ca_bundle: Some("/nonexistent/ca.pem".into()),
+            ..Default::default()
+        };
+        assert!(HttpClient::new(&config).is_err());
+
+        let config = HttpConfig {
+            proxy: Some("not a url".into()),
+            ..Default::default()
+        };
+        assert!(HttpClient::new(&config).is_err());
+    }
+
+    #[test]
+    fn test_token_for() {
+        let mut config = HttpConfig::default();
+        config.tokens.insert("corp.io".into(), "corp".into());
+        config.tokens.insert("git.corp.io".into(), "git".into());
+        let client = client(config);
+        assert_eq!(client.token_for("git.corp.io"), Some("git"));
+        assert_eq!(client.token_for("docs.corp.io"), Some("corp"));
+        assert_eq!(client.token_for("notcorp.io"), None);
+    }
+
+    #[test]
+    fn test_pem_certificates() {
+        let pem = "junk\n-----BEGIN CERTIFICATE-----\nA\n-----END CERTIFICATE-----\n-----BEGIN CERTIFICATE-----\nB\n-----END CERTIFICATE-----\n";
+        let certificates = pem_certificates(pem);
+        assert_eq!(certificates.len(), 2);
+        assert!(certificates[1].contains("\nB\n"));
+        assert!(pem_certificates("nothing").is_empty());
+    }
+}
@@ -1,31 +1,78 @@
 use std::collections::HashMap;
 use std::io::{Result, Write};
-use std::path::PathBuf;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
 
+use crate::app::config::{
+    self, masked_config_value, set_config_value, update_pinned_paths, CONFIG_KEYS,
+};
+use crate::app::discover::{find_project_root, scan_projects};
+use crate::app::doc::{project_aliases, Anchors, Theme};
+use crate::app::editor::{editor_command, find_command_line, resolve_editor};
+use crate::app::export::{export_site, page_name, site_search_entries};
+use crate::app::gfm::pipe_tables;
+use crate::app::http::HttpClient;
 use crate::app::log;
-use crate::app::parser::parse_project_command;
+use crate::app::overrides::{apply_overrides, LOCAL_SALT_FILE};
+use crate::app::parser::{parse_project_command, split_env_block};
+use crate::app::paths::SaltPaths;
+use crate::app::remote::{cache_name, resolve_doc_url};
+use crate::app::resolver::resolve_project;
+use crate::app::search::{format_hits, search, search_terms};
+use crate::app::server::{DocServer, DEFAULT_PORT};
+use crate::app::term::{find_section, page, TermRenderer};
 
 use super::ProjectDefinition;
-use super::{ProjectMap, SaltConfig};
+use super::{DocAssets, DocsConfig, ProjectMap, SaltConfig};
 
-static HBS_FILE: &str = include_str!("../../templates/salt.hbs");
 static INIT_HBS_FILE: &str = include_str!("../../templates/init.hbs");
 
-static SALT_HBS_NAME: &str = "salt.hbs";
 static INIT_HBS_NAME: &str = "init.hbs";
 
 /// INTRINSICS are commands which are internal to salt projectr
-const INTRINSICS: [(&str, &str, &str); 10] = [
+const INTRINSICS: [(&str, &str, &str); 15] = [
     ("init", "i", "Initialize new salt project in this directory"),
     ("add", "a", "Adds a salt bundle to your machine"),
-    ("doc", "d", "Opens SALT package doc as a HTML page"),
-    ("edit", "e", "Open the project/project in your editor"),
+    (
+        "doc",
+        "d",
+        "Opens SALT package doc in the browser or terminal",
+    ),
+    (
+        "edit",
+        "e",
+        "Open a project or one of its commands in your editor",
+    ),
     // ("clone", "c", "Clones a salt repo and pins it"),
-    // ("update", "u", "Update a salt project"),
-    ("pin", "p", "pin a folder as a salt project"),
+    (
+        "update",
+        "u",
+        "fetch the git includes of bundles which are missing",
+    ),
+    (
+        "pin",
+        "p",
+        "pin a folder as a salt project, --as {ALIAS} to rename",
+    ),
+    ("pins", "", "list pinned projects, prune drops missing ones"),
     ("open", "o", "open a salt project in default file explorer"),
+    (
+        "search",
+        "s",
+        "search the docs and commands of every project",
+    ),
+    (
+        "config",
+        "cfg",
+        "get, set, unset, list or edit salt settings",
+    ),
     ("unpin", "unp", "unpin a pinned salt project"),
+    (
+        "scan",
+        "sc",
+        "find salt projects inside a folder and pin them",
+    ),
     (
         "jump",
         "j",
@@ -36,8 +83,7 @@ const INTRINSICS: [(&str, &str, &str); 10] = [
 ];
 
 pub struct Interface {
-    cache_path: PathBuf,
-    projects_path: PathBuf,
+    paths: SaltPaths,
     projects: Vec<ProjectDefinition>,
     /// this is to check if there is project conflict
     project_map: ProjectMap,
@@ -76,58 +122,11 @@ fn load_envs(state: &mut Interface) -> Result<()> {
 }
 
 fn load_config(state: &mut Interface) -> Result<()> {
-    if let Some(home) = home::home_dir() {
-        state.cache_path = home.join(".salt");
-        state.projects_path = home.join("salt_projects");
-
-        // check if config folder exists inside cache path
-        let salt_config_path = state.cache_path.join(".config");
-        match salt_config_path.exists() {
-            true => {
-                if let Ok(config_str) = std::fs::read_to_string(salt_config_path) {
-                    let c = serde_json::from_str::<SaltConfig>(&config_str)
-                        .expect("error while reading salt config");
-                    log!("salt config {:?}", &c);
-                    state.config = Some(c.clone());
-                    state.full_config = Some(c);
-                }
-            }
-            false => {
-                let cfg = SaltConfig {
-                    editor: Some("vi".into()),
-                    pinned_paths: HashMap::new(),
-                };
-                write_config(&cfg)?;
-                state.config = Some(cfg.clone());
-                state.full_config = Some(cfg);
-            }
-        }
-
-        // if salt workspace folder is missing inside home directory
-        // create it
-        if !state.projects_path.exists() {
-            std::fs::create_dir(&state.projects_path)?;
-        }
-    }
-    Ok(())
-}
-
-fn write_config(c: &SaltConfig) -> Result<()> {
-    if let Some(home) = home::home_dir() {
-        // if cache directory is not there create one
-        let cache_dir = home.join(".salt");
-        if !cache_dir.exists() {
-            log!("cache dir not found | creating one");
-            std::fs::create_dir(&cache_dir)?;
-        }
-
-        // write json config
-        log!("writing initial config");
-        let cfg_path = cache_dir.join(".config");
-        let mut cfg_file = std::fs::File::create(cfg_path)?;
-        let c = serde_json::to_string_pretty(&c).unwrap();
-        cfg_file.write_all(c.as_bytes())?;
-    }
+    log!("salt paths {:?}", &state.paths);
+    let c = config::load_config(&state.paths.config_file)?;
+    log!("salt config {:?}", &c);
+    state.config = Some(c.clone());
+    state.full_config = Some(c);
     Ok(())
 }
 
@@ -142,12 +141,17 @@ fn load_workspaces(_state: &mut Interface) -> Result<()> {
     Ok(())
 }
 
-fn parse_project_from_path(path: &PathBuf) -> Result<ProjectDefinition> {
-    let md_str = std::fs::read_to_string(path).unwrap().to_string();
-    let tokens = markdown::tokenize(&md_str);
-    // println!("tok: {tokens:?}");
+pub(crate) fn parse_project_from_path(path: &PathBuf) -> Result<ProjectDefinition> {
+    let md_str = std::fs::read_to_string(path)?;
+    let tokens = markdown::tokenize(&pipe_tables(&md_str));
+    log!("markdown tokens: {tokens:?}");
     // TODO: return error if processed is false
-    Ok(crate::app::ProjectDefinition::from(tokens))
+    let mut def = crate::app::ProjectDefinition::from(tokens);
+    // local overrides of a developer are layered on top of the shared SALT.md
+    if let Some(project_dir) = path.parent() {
+        apply_overrides(&mut def, project_dir)?;
+    }
+    Ok(def)
 }
 
 fn is_project_a_intrinsic(project_name: &str) -> bool {
@@ -161,18 +165,19 @@ fn is_project_a_intrinsic(project_name: &str) -> bool {
 
 fn load_current_dir_project(state: &mut Interface) -> Result<()> {
     let cwd = std::env::current_dir().unwrap();
-    let saltmd = cwd.join("SALT.md");
-    if !saltmd.exists() {
-        log!("not a salt project or project");
-        return Ok(());
-    }
-    let md_str = std::fs::read_to_string(saltmd).unwrap().to_string();
-    let tokens = markdown::tokenize(&md_str);
-    log!("markdown tokens: {tokens:?}");
+    // salt can be invoked from any folder inside the project
+    let root = match find_project_root(&cwd) {
+        Some(root) => root,
+        None => {
+            log!("not a salt project or project");
+            return Ok(());
+        }
+    };
+    let saltmd = root.join("SALT.md");
 
     let mut marked_key = String::new();
     for (k, v) in state.config.as_mut().unwrap().pinned_paths.iter() {
-        if v == cwd.to_str().unwrap() {
+        if v == root.to_str().unwrap() {
             marked_key = k.clone();
         }
     }
@@ -185,8 +190,12 @@ fn load_current_dir_project(state: &mut Interface) -> Result<()> {
             .pinned_paths
             .remove_entry(&marked_key);
     }
-    let mut def = crate::app::ProjectDefinition::from(tokens);
+    let mut def = parse_project_from_path(&saltmd)?;
     log!("this project: {def:?}");
+    if !marked_key.is_empty() {
+        // project is pinned under an alias, keep addressing it the same way
+        def.options.name = marked_key;
+    }
     if def.options.name.is_empty() {
         println!(
             "current salt {package} doesn't have a name!",
@@ -204,23 +213,19 @@ fn load_current_dir_project(state: &mut Interface) -> Result<()> {
         ));
     }
 
-    if state.project_map.contains_key(&def.options.name) {
-        println!(
-            "there is a name conflict for project: {} at path: ./SALT.md",
-            def.options.name
-        );
-        return Ok(());
+    def.exec_path = root.clone();
+    def.project_path = root;
+    if insert_project(state, def.clone(), saltmd.to_str().unwrap()) && def.is_bundle() {
+        let mut stack = vec![def.project_path.join("SALT.md").canonicalize()?];
+        load_bundle_includes(state, &def, &mut stack)?;
     }
-    def.exec_path = cwd.clone();
-    def.project_path = cwd;
-    state.projects.push(def.clone());
-    state.project_map.insert(def.options.name.clone(), def);
 
     Ok(())
 }
 
 fn load_pinned_projects(state: &mut Interface) -> Result<()> {
-    for (_, mpath_str) in state.config.as_ref().unwrap().pinned_paths.iter() {
+    let pinned_paths = state.config.as_ref().unwrap().pinned_paths.clone();
+    for (pin_name, mpath_str) in pinned_paths.iter() {
         let mpath = std::path::PathBuf::from(mpath_str);
         log!("pinned path: {mpath:?}");
         if !mpath.join("SALT.md").exists() {
@@ -228,12 +233,19 @@ fn load_pinned_projects(state: &mut Interface) -> Result<()> {
             continue;
         }
 
-        let mut project = parse_project_from_path(&mpath.join("SALT.md"))?;
+        // an unreadable pin is reported by `s pins`, it does not stop salt
+        let mut project = match parse_project_from_path(&mpath.join("SALT.md")) {
+            Ok(project) => project,
+            Err(e) => {
+                log!("pinned path: {mpath:?} can not be read: {}", e);
+                continue;
+            }
+        };
+        // the pinned name can be an alias given with `s pin --as`
+        project.options.name = pin_name.clone();
         project.is_pinned = true;
         project.project_path = mpath.clone();
         project.exec_path = mpath.clone();
-        // TODO: can be extracted as a function .. the same code is used to
-        // load added project and
         if is_project_a_intrinsic(project.options.name.as_str()) {
             return Err(std::io::Error::new(
                 std::io::ErrorKind::Unsupported,
@@ -243,18 +255,156 @@ fn load_pinned_projects(state: &mut Interface) -> Result<()> {
                 ),
             ));
         }
-        if state.project_map.contains_key(&project.options.name) {
+        if insert_project(state, project.clone(), mpath_str) && project.is_bundle() {
+            let mut stack = vec![mpath.join("SALT.md").canonicalize()?];
+            load_bundle_includes(state, &project, &mut stack)?;
+        }
+    }
+    Ok(())
+}
+
+/// insert_project adds the project to the project map under its qualified
+/// name, returns false if there is a name conflict
+fn insert_project(state: &mut Interface, project: ProjectDefinition, location: &str) -> bool {
+    let name = project.qualified_name();
+    if state.project_map.contains_key(&name) {
+        println!(
+            "there is a name conflict for project: {} at path: {}",
+            name, location
+        );
+        return false;
+    }
+    state.projects.push(project.clone());
+    state.project_map.insert(name, project);
+    true
+}
+
+fn is_git_link(link: &str) -> bool {
+    ["https://", "http://", "ssh://", "git://", "git@"]
+        .iter()
+        .any(|scheme| link.starts_with(scheme))
+}
+
+/// bundle_clone_dir is the folder inside the bundles cache a git include is
+/// cloned into
+fn bundle_clone_dir(paths: &SaltPaths, include: &str) -> PathBuf {
+    let dir_name = include
+        .trim_end_matches('/')
+        .trim_end_matches(".git")
+        .split("://")
+        .last()
+        .unwrap()
+        .replace(['/', ':', '@'], "_");
+    paths.bundles_dir().join(dir_name)
+}
+
+/// clones a git bundle include into clone_dir
+fn clone_bundle_include(include: &str, clone_dir: &Path) -> Result<()> {
+    println!("fetching bundle include: {}", include);
+    let mut clone_cmd = std::process::Command::new("git");
+    clone_cmd.args([
+        "clone",
+        "--depth",
+        "1",
+        include,
+        clone_dir.to_str().unwrap(),
+    ]);
+    clone_cmd.stdout(Stdio::null());
+    if !clone_cmd.status()?.success() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("error while cloning bundle include: {}", include),
+        ));
+    }
+    Ok(())
+}
+
+/// resolves a bundle include to the path of its SALT.md file, git links are
+/// never fetched here, one which is not in the bundles cache yet is reported
+/// as NotFound until `s update` clones it
+fn resolve_bundle_include(state: &Interface, base: &Path, include: &str) -> Result<PathBuf> {
+    if is_git_link(include) {
+        let clone_dir = bundle_clone_dir(&state.paths, include);
+        if !clone_dir.exists() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("{} is not fetched yet, run `s update` to fetch it", include),
+            ));
+        }
+        return Ok(clone_dir.join("SALT.md"));
+    }
+
+    let path = base.join(include);
+    if path.is_dir() {
+        return Ok(path.join("SALT.md"));
+    }
+    Ok(path)
+}
+
+/// load_bundle_includes recursively loads the projects included by a bundle
+/// and registers them as `bundle/project`. stack holds the SALT.md files of
+/// the bundles currently being loaded and is used to detect include cycles
+fn load_bundle_includes(
+    state: &mut Interface,
+    bundle: &ProjectDefinition,
+    stack: &mut Vec<PathBuf>,
+) -> Result<()> {
+    for include in bundle.includes.iter() {
+        let saltmd = match resolve_bundle_include(state, &bundle.project_path, include) {
+            Ok(saltmd) if saltmd.exists() => saltmd.canonicalize()?,
+            Ok(saltmd) => {
+                println!(
+                    "bundle {} includes {} which does not contain SALT.md",
+                    bundle.qualified_name(),
+                    saltmd.to_string_lossy()
+                );
+                continue;
+            }
+            Err(e) => {
+                println!("bundle {}: {}", bundle.qualified_name(), e);
+                continue;
+            }
+        };
+        if stack.contains(&saltmd) {
+            let mut cycle = stack
+                .iter()
+                .map(|p| p.to_string_lossy().to_string())
+                .collect::<Vec<String>>();
+            cycle.push(saltmd.to_string_lossy().to_string());
+            println!("bundle include cycle detected: {}", cycle.join(" -> "));
+            continue;
+        }
+
+        let mut project = match parse_project_from_path(&saltmd) {
+            Ok(project) => project,
+            Err(e) => {
+                println!(
+                    "bundle {} includes {} which can not be read: {}",
+                    bundle.qualified_name(),
+                    saltmd.to_string_lossy(),
+                    e
+                );
+                continue;
+            }
+        };
+        if project.options.name.is_empty() {
             println!(
-                "there is a name conflict for project: {} at path: {}",
-                project.options.name,
-                mpath.to_str().unwrap()
+                "salt {} at path: {} doesn't have a name!",
+                project.options.typ,
+                saltmd.to_string_lossy()
             );
             continue;
         }
-        state.projects.push(project.clone());
-        state
-            .project_map
-            .insert(project.options.name.clone(), project);
+        let project_dir = saltmd.parent().unwrap().to_path_buf();
+        project.bundle = Some(bundle.qualified_name());
+        project.is_pinned = bundle.is_pinned;
+        project.project_path = project_dir.clone();
+        project.exec_path = project_dir;
+        if insert_project(state, project.clone(), saltmd.to_str().unwrap()) && project.is_bundle() {
+            stack.push(saltmd);
+            load_bundle_includes(state, &project, stack)?;
+            stack.pop();
+        }
     }
     Ok(())
 }
@@ -273,15 +423,100 @@ fn open_explorer(path: &str) -> Result<()> {
     Ok(())
 }
 
-fn is_cwd_salt_project() -> Result<bool> {
-    Ok(std::env::current_dir()?.join("SALT.md").exists())
+enum PinStatus {
+    Ok,
+    Missing,
+    /// the pin name differs from the name in SALT.md, either because the project
+    /// was pinned with an alias or because it was renamed after pinning
+    NameMismatch(String),
+    /// the SALT.md is there but can not be read, it is never pruned as the
+    /// project may be fine once it can be read again
+    Unreadable(String),
+}
+
+fn pin_status(name: &str, path: &str) -> PinStatus {
+    let saltmd = PathBuf::from(path).join("SALT.md");
+    if !saltmd.exists() {
+        return PinStatus::Missing;
+    }
+    match parse_project_from_path(&saltmd) {
+        Ok(project) if project.options.name != name => {
+            PinStatus::NameMismatch(project.options.name)
+        }
+        Ok(_) => PinStatus::Ok,
+        Err(e) => PinStatus::Unreadable(e.to_string()),
+    }
+}
+
+fn validate_pin_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.contains('/') {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("{} is not a valid project name", name),
+        ));
+    }
+    if is_project_a_intrinsic(name) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            format!(
+                "cannot use {} as a project name, it is an intrinsic command",
+                name
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// returns the root folder of the salt project the current directory is in
+fn cwd_project_root() -> Result<Option<PathBuf>> {
+    Ok(find_project_root(&std::env::current_dir()?))
+}
+
+/// anchor_section finds the title of the section of project which is, or
+/// has a heading, with the id anchor
+fn anchor_section(project: &ProjectDefinition, anchor: &str) -> Result<String> {
+    let anchors = Anchors::new(project);
+    anchors.section_of(project, anchor).cloned().ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!(
+                "#{} not found in {}, sections are: {}",
+                anchor,
+                project.qualified_name(),
+                anchors
+                    .sections
+                    .iter()
+                    .map(|id| format!("#{}", id))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+        )
+    })
+}
+
+/// is_ssh_session is true when salt runs over ssh without a display to open
+/// a browser on
+fn is_ssh_session() -> bool {
+    let over_ssh =
+        std::env::var_os("SSH_CONNECTION").is_some() || std::env::var_os("SSH_TTY").is_some();
+    let has_display =
+        std::env::var_os("DISPLAY").is_some() || std::env::var_os("WAYLAND_DISPLAY").is_some();
+    over_ssh && !has_display
+}
+
+/// asks a yes/no question on the terminal, anything other than y/yes is a no
+fn confirm(question: &str) -> Result<bool> {
+    print!("{} [y/N] ", question);
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
 }
 
 impl Interface {
     pub fn init() -> Result<Self> {
         let mut app = Self {
-            cache_path: PathBuf::new(),
-            projects_path: PathBuf::new(),
+            paths: SaltPaths::resolve()?,
             project_map: HashMap::new(),
             projects: vec![],
             config: None,
@@ -296,15 +531,13 @@ impl Interface {
         Ok(app)
     }
 
-    pub fn save_to_history(&self, args: &Vec<String>) -> Result<()> {
+    pub fn save_to_history(&self, args: &[String]) -> Result<()> {
         if args.len() <= 2 {
             return Ok(());
         }
-        if let Some(home) = home::home_dir() {
-            let history_file_path = home.join(".salt").join(".history");
-            let mut hfile = std::fs::File::create(history_file_path)?;
-            hfile.write_all(args.join(" ").as_bytes())?;
-        }
+        std::fs::create_dir_all(&self.paths.data_dir)?;
+        let mut hfile = std::fs::File::create(self.paths.history_file())?;
+        hfile.write_all(args.join(" ").as_bytes())?;
         Ok(())
     }
 
@@ -315,12 +548,16 @@ impl Interface {
                 "init" | "i" => self.init_project()?,
                 "add" | "a" => self.add_project(args.get(2))?,
                 "edit" | "e" => self.open_editor(args)?,
-                // "update" | "u" => self.update_projects()?,
+                "update" | "u" => self.update_bundles()?,
                 "workspace" | "w" => self.load_workspace(args)?,
                 "open" | "o" => self.open_project(args)?,
                 "doc" | "d" => self.open_doc(args)?,
-                "pin" | "p" => self.pin_project()?,
+                "search" | "s" => self.search_docs(args)?,
+                "config" | "cfg" => self.config_cmd(args)?,
+                "pin" | "p" => self.pin_project(args)?,
+                "pins" => self.list_pins(args)?,
                 "unpin" | "unp" => self.unpin_project(args)?,
+                "scan" | "sc" => self.scan_for_projects(args)?,
                 "jump" | "j" => self.jump_to_project(args)?,
                 // "clone" | "c" => self.clone_salt_repo(args)?,
                 // "install" | "-in" => self.install_deps()?,
@@ -339,135 +576,357 @@ impl Interface {
         Ok(())
     }
 
+    /// resolves a project address given on the command line, see
+    /// [resolve_project] for the addressing rules
+    fn resolve(&self, address: &str) -> Result<&ProjectDefinition> {
+        resolve_project(&self.project_map, address)
+    }
+
     fn open_editor(&self, args: &[String]) -> Result<()> {
         let config = self.config.as_ref().unwrap();
-        if config.editor.is_none() {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::NotFound,
-                "add your editor config to $HOME/.salt/.config",
-            ));
+        let editor = resolve_editor(config.editor.as_deref());
+        log!("editor: {}", &editor);
+        let (path, line) = match (args.get(2), args.get(3)) {
+            // open SALT.md at the line where the command is defined
+            (Some(project_name), Some(command)) => {
+                let project = self.resolve(project_name)?;
+                self.find_command_definition(project, command)?
+            }
+            (Some(project_name), None) => (self.resolve(project_name)?.exec_path.clone(), None),
+            (None, _) => match cwd_project_root()? {
+                Some(root) => (root, None),
+                None => return Ok(()),
+            },
+        };
+        editor_command(&editor, &path, line).status()?;
+        Ok(())
+    }
+
+    /// finds the file and line a project command is defined at, a command
+    /// overridden in SALT.local.md is found there
+    fn find_command_definition(
+        &self,
+        project: &ProjectDefinition,
+        command: &str,
+    ) -> Result<(PathBuf, Option<usize>)> {
+        for file in [LOCAL_SALT_FILE, "SALT.md"] {
+            let path = project.project_path.join(file);
+            if !path.is_file() {
+                continue;
+            }
+            if let Some(line) = find_command_line(&std::fs::read_to_string(&path)?, command) {
+                return Ok((path, Some(line)));
+            }
         }
-        if let Some(project_name) = args.get(2) {
-            if let Some(project) = self.project_map.get(project_name) {
-                let mut editor_cmd = std::process::Command::new(config.editor.as_ref().unwrap());
-                editor_cmd.arg(project.exec_path.to_str().unwrap());
-                editor_cmd.status()?;
+        Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!(
+                "command {} not found in project {}",
+                command,
+                project.qualified_name()
+            ),
+        ))
+    }
+
+    fn config_cmd(&self, args: &[String]) -> Result<()> {
+        let usage_err = Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "usage: s config [list | get {KEY} | set {KEY} {VALUE} | unset {KEY} | edit]",
+        ));
+        let c = self.full_config.as_ref().unwrap();
+        match args.get(2).map(|a| a.as_str()) {
+            None | Some("list") => {
+                for (key, kind, about) in CONFIG_KEYS {
+                    let value = masked_config_value(c, key)?;
+                    println!("{} = {}      - {} ({})", key, value, about, kind);
+                }
+            }
+            Some("get") => match args.get(3) {
+                Some(key) => match masked_config_value(c, key)? {
+                    serde_json::Value::String(value) => println!("{}", value),
+                    serde_json::Value::Null => {}
+                    value => println!("{}", value),
+                },
+                None => return usage_err,
+            },
+            Some("set") => match args.get(3..) {
+                Some([key, value @ ..]) if !value.is_empty() => {
+                    // values with spaces don't need to be quoted
+                    let value = value.join(" ");
+                    set_config_value(&self.paths.config_file, key, Some(&value))?;
+                    println!("{} = {}", key, value);
+                }
+                _ => return usage_err,
+            },
+            Some("unset") => match args.get(3) {
+                Some(key) => {
+                    set_config_value(&self.paths.config_file, key, None)?;
+                    println!("unset :: {}", key);
+                }
+                None => return usage_err,
+            },
+            Some("edit") => {
+                let editor = resolve_editor(c.editor.as_deref());
+                editor_command(&editor, &self.paths.config_file, None).status()?;
             }
-        } else if is_cwd_salt_project()? {
-            let mut editor_cmd = std::process::Command::new(config.editor.as_ref().unwrap());
-            editor_cmd.arg(std::env::current_dir().unwrap());
-            editor_cmd.status()?;
+            Some(_) => return usage_err,
         }
         Ok(())
     }
 
     fn open_doc_from_web(&self, link: &str) -> Result<()> {
-        if link.contains("github.com") {
-            let mut raw_gh_link = link.replace("github.com", "raw.githubusercontent.com");
-            if !raw_gh_link.ends_with("SALT.md") && !raw_gh_link.ends_with('/') {
-                raw_gh_link.push_str("/main/SALT.md");
-            } else if !raw_gh_link.ends_with("SALT.md") {
-                raw_gh_link.push_str("SALT.md");
-            }
-            println!("hitting: {}", raw_gh_link);
-            let resp = reqwest::blocking::get(&raw_gh_link)
-                .unwrap()
-                .text()
-                .unwrap();
-            let tokens = markdown::tokenize(&resp);
-            let project = crate::app::ProjectDefinition::from(tokens);
-            let doc = crate::app::doc::Doc::from(project);
-            let doc_html_name = raw_gh_link
-                .replace("https://", "")
-                .replace("http://", "")
-                .replace('/', "")
-                .replace("raw.githubusercontent.com", "")
-                .trim_start_matches('-')
-                .to_owned();
-            let doc_path = self.cache_path.join(format!("{}.html", doc_html_name));
-            let mut reg = handlebars::Handlebars::new();
-            // TODO: handle unwrap
-            reg.register_template_string(SALT_HBS_NAME, HBS_FILE)
-                .unwrap();
-            let html = reg.render(SALT_HBS_NAME, &doc).unwrap();
-            std::fs::write(doc_path.clone(), html)?;
-            webbrowser::open_browser(webbrowser::Browser::Default, doc_path.to_str().unwrap())?;
-        }
+        let raw_link = resolve_doc_url(link)?;
+        println!("hitting: {}", raw_link);
+        // the client is only needed for this one request, so it is built here
+        let resp = HttpClient::new(&self.config.as_ref().unwrap().http)?.get_text(&raw_link)?;
+        let tokens = markdown::tokenize(&pipe_tables(&resp));
+        let project = crate::app::ProjectDefinition::from(tokens);
+        let theme = Theme::load(&project, self.docs_config())?;
+        // relative images are next to the SALT.md on the host
+        let image_base = raw_link.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("");
+        let mut doc = crate::app::doc::Doc::with_image_base(project, image_base);
+        doc.set_theme(theme);
+        doc.offline = self.docs_config().assets == DocAssets::Offline;
+        let doc_path = self.paths.cache_file(&cache_name(&raw_link))?;
+        let html = doc.render_html()?;
+        std::fs::write(doc_path.clone(), html)?;
+        webbrowser::open_browser(webbrowser::Browser::Default, doc_path.to_str().unwrap())?;
         Ok(())
     }
 
+    fn docs_config(&self) -> &DocsConfig {
+        &self.config.as_ref().unwrap().docs
+    }
+
     fn open_salt_doc(&self) -> Result<()> {
         webbrowser::open_browser(webbrowser::Browser::Default, "https://saltybun.github.io")?;
         Ok(())
     }
 
     fn open_doc(&self, args: &[String]) -> Result<()> {
-        if let Some(project_name) = args.get(2) {
-            if project_name.eq("help") {
-                return self.open_salt_doc();
-            }
-            if project_name.starts_with("https") || project_name.starts_with("http") {
-                return self.open_doc_from_web(project_name);
-            }
-            if let Some(project) = self.project_map.get(project_name) {
-                let doc = crate::app::doc::Doc::from(project.to_owned());
-                let doc_path = self.cache_path.join(format!("{}.html", project_name));
-                let mut reg = handlebars::Handlebars::new();
-                // TODO: handle unwrap
-                reg.register_template_string(SALT_HBS_NAME, HBS_FILE)
-                    .unwrap();
-                let html = reg.render(SALT_HBS_NAME, &doc).unwrap();
-                std::fs::write(doc_path.clone(), html)?;
-                webbrowser::open_browser(webbrowser::Browser::Default, doc_path.to_str().unwrap())?;
-            } else {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::NotFound,
-                    "no such project",
-                ));
+        // docs are read in the terminal when asked for, or when there likely
+        // is no browser to open them in, like over ssh
+        let mut flags = vec![];
+        let mut positional = vec![];
+        let mut port = None;
+        let mut export_dir = None;
+        let mut rest = args.iter().skip(2);
+        while let Some(arg) = rest.next() {
+            match arg.as_str() {
+                "--port" => port = rest.next(),
+                "--export" => export_dir = rest.next(),
+                flag if flag.starts_with('-') => flags.push(flag),
+                _ => positional.push(arg),
+            }
+        }
+        if let Some(export_dir) = export_dir {
+            let count = export_site(
+                &self.project_map,
+                &PathBuf::from(export_dir),
+                self.docs_config(),
+            )?;
+            println!("exported {} projects to {}", count, export_dir);
+            return Ok(());
+        }
+        // `project#anchor` opens the doc at a section or heading
+        let (project_name, anchor) = match positional.first() {
+            Some(name) if !name.starts_with("http") => match name.split_once('#') {
+                Some((name, anchor)) => (Some(name), Some(anchor)),
+                None => (Some(name.as_str()), None),
+            },
+            other => (other.map(|n| n.as_str()), None),
+        };
+        if flags.iter().any(|f| *f == "--serve" || *f == "-s") {
+            return self.serve_docs(project_name, anchor, port);
+        }
+        let term = flags.iter().any(|f| *f == "--term" || *f == "-t") || is_ssh_session();
+
+        let project = match project_name {
+            Some(name) if name.eq("help") => return self.open_salt_doc(),
+            Some(name) if name.starts_with("https") || name.starts_with("http") => {
+                return self.open_doc_from_web(name);
+            }
+            Some(name) => self.resolve(name)?.to_owned(),
+            None => match cwd_project_root()? {
+                Some(root) => parse_project_from_path(&root.join("SALT.md"))?,
+                None => return Ok(()),
+            },
+        };
+
+        let section = match anchor {
+            Some(anchor) => Some(anchor_section(&project, anchor)?),
+            None => positional.get(1).map(|s| s.to_string()),
+        };
+
+        if term {
+            let renderer = TermRenderer::for_stdout();
+            return page(&renderer.render(&project, section.as_deref())?);
+        }
+
+        let mut doc = crate::app::doc::Doc::from(project.to_owned());
+        doc.set_theme(Theme::load(&project, self.docs_config())?);
+        doc.aliases = project_aliases(self.project_map.values(), &project);
+        doc.offline = self.docs_config().assets == DocAssets::Offline;
+        if let Some(section) = &section {
+            // reuses the error listing the sections of the project
+            if !doc.activate(section) {
+                find_section(&project, section)?;
             }
-        } else if std::env::current_dir()?.join("SALT.md").exists() {
-            let project = parse_project_from_path(&std::env::current_dir()?.join("SALT.md"))?;
-            let doc = crate::app::doc::Doc::from(project.to_owned());
-            let doc_path = self
-                .cache_path
-                .join(format!("{}.html", project.options.name));
-            let mut reg = handlebars::Handlebars::new();
-            // TODO: handle unwrap
-            reg.register_template_string(SALT_HBS_NAME, HBS_FILE)
-                .unwrap();
-            let html = reg.render(SALT_HBS_NAME, &doc).unwrap();
-            std::fs::write(doc_path.clone(), html)?;
-            webbrowser::open_browser(webbrowser::Browser::Default, doc_path.to_str().unwrap())?;
         }
+        // bundle members are named bundle/project, keep the doc file flat
+        let doc_path = self.paths.cache_file(&format!(
+            "{}.html",
+            project.qualified_name().replace('/', "--")
+        ))?;
+        let html = doc.render_html()?;
+        std::fs::write(doc_path.clone(), html)?;
+        let url = match anchor {
+            Some(anchor) => format!("file://{}#{}", doc_path.to_string_lossy(), anchor),
+            None => doc_path.to_string_lossy().into_owned(),
+        };
+        webbrowser::open_browser(webbrowser::Browser::Default, &url)?;
+        Ok(())
+    }
 
+    fn search_docs(&self, args: &[String]) -> Result<()> {
+        let terms = search_terms(args.get(2..).unwrap_or_default());
+        if terms.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "usage: s search <terms>",
+            ));
+        }
+        let entries = site_search_entries(&self.project_map, page_name);
+        let hits = search(&entries, &terms);
+        if hits.is_empty() {
+            println!("nothing found for {}", terms.join(" "));
+            return Ok(());
+        }
+        let renderer = TermRenderer::for_stdout();
+        print!("{}", format_hits(&hits, &terms, &renderer));
         Ok(())
     }
 
+    fn serve_docs(
+        &self,
+        project_name: Option<&str>,
+        anchor: Option<&str>,
+        port: Option<&String>,
+    ) -> Result<()> {
+        let port = match port {
+            Some(port) => port.parse::<u16>().map_err(|_| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("invalid port: {}", port),
+                )
+            })?,
+            None => DEFAULT_PORT,
+        };
+        // only this machine can read the docs
+        let listener = TcpListener::bind(("127.0.0.1", port)).map_err(|e| {
+            std::io::Error::new(
+                e.kind(),
+                format!("cannot serve docs on port {}: {}", port, e),
+            )
+        })?;
+
+        // open the doc of the project asked for, or of the current one
+        let page = match project_name {
+            Some(name) => {
+                let project = self.resolve(name)?;
+                match anchor {
+                    Some(anchor) => {
+                        anchor_section(project, anchor)?;
+                        format!("{}#{}", DocServer::project_link(project), anchor)
+                    }
+                    None => DocServer::project_link(project),
+                }
+            }
+            None => match cwd_project_root()? {
+                Some(root) => self
+                    .project_map
+                    .values()
+                    .find(|p| p.project_path.canonicalize().ok().as_ref() == Some(&root))
+                    .map(DocServer::project_link)
+                    .unwrap_or("/".into()),
+                None => "/".into(),
+            },
+        };
+        let url = format!("http://127.0.0.1:{}{}", port, page);
+        println!(
+            "serving docs at http://127.0.0.1:{}, press ctrl+c to stop",
+            port
+        );
+        if webbrowser::open_browser(webbrowser::Browser::Default, &url).is_err() {
+            println!("open {} in your browser", url);
+        }
+        DocServer::new(self.project_map.clone(), self.docs_config().clone()).serve(listener)
+    }
+
     fn open_project(&self, args: &[String]) -> Result<()> {
         if let Some(project_name) = args.get(2) {
-            if let Some(project) = self.project_map.get(project_name) {
-                log!("has project");
-                return open_explorer(project.exec_path.to_str().unwrap());
-            }
+            let project = self.resolve(project_name)?;
+            log!("has project");
+            return open_explorer(project.exec_path.to_str().unwrap());
         }
         open_explorer("")
     }
 
     fn unpin_project(&self, args: &[String]) -> Result<()> {
-        let not_found_err = Err(std::io::Error::new(
-            std::io::ErrorKind::NotFound,
-            "project not found",
-        ));
-        if let Some(project_name) = args.get(2) {
-            if self.project_map.get(project_name).is_some() {
-                let mut c = self.full_config.clone().unwrap();
-                c.pinned_paths.remove(project_name);
-                log!("config: {:?}", &c);
-                return write_config(&c);
+        let pinned_paths = &self.full_config.as_ref().unwrap().pinned_paths;
+        let pin_name = match args.get(2) {
+            // unpin by the pinned name
+            Some(target) if pinned_paths.contains_key(target) => target.to_owned(),
+            // unpin by the path of a pinned folder
+            Some(target) if PathBuf::from(target).exists() => {
+                self.pin_name_for_path(&PathBuf::from(target).canonicalize()?)?
             }
-        }
+            Some(target) => {
+                let project = self.resolve(target)?;
+                if let Some(bundle) = &project.bundle {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::Unsupported,
+                        format!(
+                            "{} is a part of bundle {}, unpin the bundle instead",
+                            project.qualified_name(),
+                            bundle
+                        ),
+                    ));
+                }
+                self.pin_name_for_path(&project.project_path)?
+            }
+            // unpin the project we are in
+            None => match cwd_project_root()? {
+                Some(root) => self.pin_name_for_path(&root)?,
+                None => {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::NotFound,
+                        "not a salt project, usage: s unpin {PROJECT | PATH}",
+                    ))
+                }
+            },
+        };
 
-        not_found_err
+        let path = pinned_paths.get(&pin_name).unwrap().to_owned();
+        update_pinned_paths(&self.paths.config_file, |pins| {
+            pins.remove(&pin_name);
+        })?;
+        println!("unpinned :: {} :: {}", pin_name, path);
+        Ok(())
+    }
+
+    /// finds the name a folder is pinned with
+    fn pin_name_for_path(&self, path: &Path) -> Result<String> {
+        let pinned_paths = &self.full_config.as_ref().unwrap().pinned_paths;
+        for (name, pinned_path) in pinned_paths.iter() {
+            if Path::new(pinned_path) == path {
+                return Ok(name.to_owned());
+            }
+        }
+        Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("{} is not pinned", path.to_string_lossy()),
+        ))
     }
 
     fn jump_to_project(&self, args: &[String]) -> Result<()> {
@@ -476,26 +935,21 @@ impl Interface {
             "project not found",
         ));
         if let Some(project_name) = args.get(2) {
-            if let Some(project) = self.project_map.get(project_name) {
-                println!("{}", project.exec_path.to_str().unwrap());
-                return Ok(());
-            }
-            return not_found_err;
+            let project = self.resolve(project_name)?;
+            println!("{}", project.exec_path.to_str().unwrap());
+            return Ok(());
         }
         not_found_err
     }
 
     fn run_last_cmd(&mut self) -> Result<()> {
-        if let Some(home) = home::home_dir() {
-            let history_file_path = home.join(".salt").join(".history");
-            let cmd_str = std::fs::read_to_string(history_file_path)?;
-            self.run(
-                &cmd_str
-                    .split(' ')
-                    .map(|s| s.to_owned())
-                    .collect::<Vec<String>>(),
-            )?;
-        }
+        let cmd_str = std::fs::read_to_string(self.paths.history_file())?;
+        self.run(
+            &cmd_str
+                .split(' ')
+                .map(|s| s.to_owned())
+                .collect::<Vec<String>>(),
+        )?;
         Ok(())
     }
 
@@ -503,11 +957,17 @@ impl Interface {
         if let Some(project_name) = args.get(2) {
             // if we have a project with the name given as 2nd arg
             // run the command of the project
-            if let Some(project) = self.project_map.get(project_name) {
+            let project = match self.resolve(project_name) {
+                Ok(project) => Some(project),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                Err(e) => return Err(e),
+            };
+            if let Some(project) = project {
                 std::env::set_current_dir(&project.exec_path)?;
                 if let Some(cmd) = args.get(3) {
                     let mut some_cmd = std::process::Command::new(cmd);
                     some_cmd.envs(&self.env_vars);
+                    some_cmd.envs(&project.env);
                     if let Some(cmd_args) = args.get(4..) {
                         some_cmd.args(cmd_args);
                     }
@@ -556,21 +1016,253 @@ impl Interface {
         Ok(())
     }
 
-    fn pin_project(&self) -> Result<()> {
-        let cwd = std::env::current_dir()?;
-        if !cwd.join("SALT.md").exists() {
+    /// update_bundles clones the git includes of loaded bundles which are not
+    /// in the bundles cache yet, a fetched include which is a bundle itself
+    /// gets its own git includes fetched too
+    fn update_bundles(&self) -> Result<()> {
+        let mut queue = self
+            .projects
+            .iter()
+            .filter(|p| p.is_bundle())
+            .cloned()
+            .collect::<Vec<ProjectDefinition>>();
+        let (mut fetched, mut failed) = (0, 0);
+        while let Some(bundle) = queue.pop() {
+            for include in bundle.includes.iter().filter(|i| is_git_link(i)) {
+                let clone_dir = bundle_clone_dir(&self.paths, include);
+                if clone_dir.exists() {
+                    continue;
+                }
+                if let Err(e) = clone_bundle_include(include, &clone_dir) {
+                    println!("bundle {}: {}", bundle.qualified_name(), e);
+                    failed += 1;
+                    continue;
+                }
+                fetched += 1;
+                if let Ok(mut project) = parse_project_from_path(&clone_dir.join("SALT.md")) {
+                    if project.is_bundle() {
+                        project.bundle = Some(bundle.qualified_name());
+                        queue.push(project);
+                    }
+                }
+            }
+        }
+        match (fetched, failed) {
+            (0, 0) => println!("all bundle includes are already fetched"),
+            (fetched, 0) => println!("fetched {} bundle include(s)", fetched),
+            (fetched, failed) => {
+                println!("fetched {} bundle include(s), {} failed", fetched, failed)
+            }
+        }
+        Ok(())
+    }
+
+    fn pin_project(&self, args: &[String]) -> Result<()> {
+        match args.get(2).map(|a| a.as_str()) {
+            Some("--move") => {
+                return match (args.get(3), args.get(4)) {
+                    (Some(name), Some(path)) => self.move_pin(name, path),
+                    _ => Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        "usage: s pin --move {PROJECT} {PATH}",
+                    )),
+                };
+            }
+            Some("--as") if args.get(3).is_none() => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "usage: s pin --as {ALIAS}",
+                ));
+            }
+            _ => {}
+        }
+
+        let root = match cwd_project_root()? {
+            Some(root) => root,
+            None => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    "not a salt project",
+                ))
+            }
+        };
+        let project = parse_project_from_path(&root.join("SALT.md"))?;
+        let name = match args.get(2).map(|a| a.as_str()) {
+            Some("--as") => args.get(3).unwrap().to_owned(),
+            _ => project.options.name,
+        };
+        validate_pin_name(&name)?;
+
+        let root_str = root.to_str().unwrap().to_owned();
+        let c = self.full_config.as_ref().unwrap();
+        match c.pinned_paths.get(&name) {
+            Some(path) if path == &root_str => {
+                println!("already pinned :: {} :: {}", name, root_str);
+                return Ok(());
+            }
+            Some(path) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    format!(
+                        "a project named {} is already pinned at {}, pin this one with `s pin --as {{ALIAS}}`",
+                        name, path
+                    ),
+                ));
+            }
+            None => {}
+        }
+        update_pinned_paths(&self.paths.config_file, |pins| {
+            // pinning an already pinned folder under a new name renames the pin
+            pins.retain(|_, path| path != &root_str);
+            pins.insert(name.clone(), root_str);
+        })?;
+
+        println!("pinned :: {} :: {}", name, root.to_string_lossy());
+        Ok(())
+    }
+
+    fn move_pin(&self, name: &str, path: &str) -> Result<()> {
+        let c = self.full_config.as_ref().unwrap();
+        if !c.pinned_paths.contains_key(name) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("no project pinned as {}", name),
+            ));
+        }
+        let new_path = PathBuf::from(path).canonicalize()?;
+        if !new_path.join("SALT.md").exists() {
             return Err(std::io::Error::new(
                 std::io::ErrorKind::NotFound,
-                "not a salt project",
+                format!("{} is not a salt project", new_path.to_string_lossy()),
+            ));
+        }
+        let new_path_str = new_path.to_str().unwrap();
+        if let Some((other, _)) = c
+            .pinned_paths
+            .iter()
+            .find(|(other, path)| other.as_str() != name && path.as_str() == new_path_str)
+        {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("{} is already pinned as {}", new_path_str, other),
             ));
         }
-        let project = parse_project_from_path(&cwd.join("SALT.md"))?;
-        let mut c = self.config.clone().unwrap();
-        c.pinned_paths
-            .insert(project.options.name, cwd.to_str().unwrap().into());
-        write_config(&c)?;
+        update_pinned_paths(&self.paths.config_file, |pins| {
+            pins.insert(name.to_owned(), new_path_str.into());
+        })?;
 
-        println!("pinned :: {}", cwd.to_string_lossy());
+        println!("moved :: {} :: {}", name, new_path.to_string_lossy());
+        Ok(())
+    }
+
+    fn list_pins(&self, args: &[String]) -> Result<()> {
+        let prune = match args.get(2).map(|a| a.as_str()) {
+            Some("prune") => true,
+            Some(_) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "usage: s pins [prune]",
+                ))
+            }
+            None => false,
+        };
+
+        let c = self.full_config.as_ref().unwrap();
+        let mut pins = c
+            .pinned_paths
+            .iter()
+            .map(|(name, path)| (name.clone(), path.clone()))
+            .collect::<Vec<(String, String)>>();
+        pins.sort();
+        if pins.is_empty() {
+            println!("no pinned projects");
+            return Ok(());
+        }
+
+        let mut pruned = vec![];
+        for (name, path) in pins {
+            let status = pin_status(&name, &path);
+            if prune {
+                if let PinStatus::Missing = status {
+                    println!("pruned :: {} :: {}", name, path);
+                    pruned.push(name);
+                }
+                continue;
+            }
+            match status {
+                PinStatus::Ok => println!("{} :: {} [ok]", name, path),
+                PinStatus::Missing => println!("{} :: {} [missing]", name, path),
+                PinStatus::NameMismatch(actual) => println!(
+                    "{} :: {} [name-mismatch: SALT.md is named {}]",
+                    name, path, actual
+                ),
+                PinStatus::Unreadable(err) => {
+                    println!("{} :: {} [unreadable: {}]", name, path, err)
+                }
+            }
+        }
+        if prune {
+            if pruned.is_empty() {
+                println!("nothing to prune");
+                return Ok(());
+            }
+            update_pinned_paths(&self.paths.config_file, |pins| {
+                pins.retain(|name, _| !pruned.contains(name))
+            })?;
+        }
+        Ok(())
+    }
+
+    fn scan_for_projects(&self, args: &[String]) -> Result<()> {
+        let scan_root = match args.get(2) {
+            Some(dir) => PathBuf::from(dir),
+            None => std::env::current_dir()?,
+        };
+        let found = scan_projects(&scan_root.canonicalize()?)?;
+        if found.is_empty() {
+            println!("no salt projects found in {}", scan_root.to_string_lossy());
+            return Ok(());
+        }
+
+        let c = self.full_config.as_ref().unwrap();
+        let mut to_pin: HashMap<String, String> = HashMap::new();
+        for dir in found {
+            let dir_str = dir.to_str().unwrap().to_owned();
+            let project = match parse_project_from_path(&dir.join("SALT.md")) {
+                Ok(project) => project,
+                Err(e) => {
+                    println!("skipping {} :: cannot read SALT.md: {}", dir_str, e);
+                    continue;
+                }
+            };
+            let name = project.options.name;
+            if name.is_empty() {
+                println!("skipping {} :: SALT.md doesn't have a name", dir_str);
+                continue;
+            }
+            // the folder can be pinned under an alias, so look for its path
+            // among every pin instead of the one named like the project
+            let pinned_as = c.pinned_paths.iter().find(|(_, path)| {
+                **path == dir_str || Path::new(path).canonicalize().ok().as_ref() == Some(&dir)
+            });
+            if let Some((pin_name, _)) = pinned_as {
+                println!("{} :: {} 📌", pin_name, dir_str);
+                continue;
+            }
+            if c.pinned_paths.contains_key(&name)
+                || to_pin.contains_key(&name)
+                || is_project_a_intrinsic(&name)
+            {
+                println!("skipping {} :: {} is already taken", dir_str, name);
+                continue;
+            }
+            if confirm(&format!("pin {} :: {}?", name, dir_str))? {
+                to_pin.insert(name, dir_str);
+            }
+        }
+        if !to_pin.is_empty() {
+            update_pinned_paths(&self.paths.config_file, |pins| pins.extend(to_pin))?;
+        }
         Ok(())
     }
 
@@ -598,55 +1290,39 @@ impl Interface {
     }
 
     fn run_project_cmd(&self, project_name: String, args: &[String]) -> Result<()> {
-        if !self.project_map.contains_key(&project_name) {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::NotFound,
-                format!("command: {} not found", project_name),
-            ));
-        }
+        let b = match self.resolve(&project_name) {
+            Ok(b) => b,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!("command: {} not found", project_name),
+                ));
+            }
+            Err(e) => return Err(e),
+        };
         if let Some(command) = args.get(2) {
-            if let Some(b) = self.project_map.get(&project_name) {
-                if let Some(c) = b.commands.get(command.as_str()) {
-                    if b.is_pinned {
-                        let mproject_path = self
-                            .config
-                            .as_ref()
-                            .unwrap()
-                            .pinned_paths
-                            .get(&b.options.name)
-                            .unwrap();
-
-                        log!("setting current working dir: {mproject_path}");
-                        std::env::set_current_dir(mproject_path)?;
-                    } else {
-                        let pwd = std::env::current_dir()
-                            .unwrap()
-                            .to_str()
-                            .unwrap()
-                            .to_owned();
-
-                        log!("setting current working dir: {pwd}");
-                        std::env::set_current_dir(pwd)?;
-                    }
-
-                    log!("running command: {}", &c.command);
-                    let mut cmd = parse_project_command(&c.command)?;
-                    cmd.envs(&self.env_vars);
-                    cmd.status()?;
-                    return Ok(());
-                }
+            if let Some(c) = b.commands.get(command.as_str()) {
+                log!("setting current working dir: {:?}", &b.exec_path);
+                std::env::set_current_dir(&b.exec_path)?;
+
+                log!("running command: {}", &c.command);
+                let mut cmd = parse_project_command(&c.command)?;
+                cmd.envs(&self.env_vars);
+                cmd.envs(&b.env);
+                // the `[KEY=value]` block of the command wins over the
+                // environment of the whole project
+                cmd.envs(split_env_block(&c.command).0);
+                cmd.status()?;
+                return Ok(());
             }
         }
 
         println!("Cannot find command in this project, here's something to work with...");
-        self.display_project_command_help(
-            project_name.as_str(),
-            self.project_map.get(&project_name).unwrap(),
-        );
+        self.display_project_command_help(project_name.as_str(), b);
         Ok(())
     }
 
-    fn get_project_name(&self, project_link: &String) -> Result<String> {
+    fn get_project_name(&self, project_link: &str) -> Result<String> {
         let mut check_remote_cmd = std::process::Command::new("git");
         check_remote_cmd.args(["ls-remote", project_link]);
         check_remote_cmd.stdout(Stdio::null());
@@ -677,54 +1353,47 @@ impl Interface {
     }
 
     fn clone_project(&self, link: &str, name: &str) -> Result<()> {
-        if let Some(home_dir) = home::home_dir() {
-            let project_dir = std::path::Path::new(home_dir.to_str().unwrap())
-                .join(".salt")
-                .join(name);
-            if project_dir.exists() {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::InvalidData,
-                    "a project with this name already exists",
-                ));
-            }
+        let project_dir = self.paths.data_dir.join(name);
+        if project_dir.exists() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "a project with this name already exists",
+            ));
+        }
 
-            // TODO: think of a way to check if it is a salt project by just
-            // checking if this remote has salt.json in it.
-            // then only clone it
-            let mut clone_cmd = std::process::Command::new("git");
-            clone_cmd.args(["clone", link, project_dir.to_str().unwrap()]);
-            match clone_cmd.status()?.code() {
-                Some(code) => {
-                    if code == 0 {
-                        let project_salt_file = project_dir.join("salt.json");
-                        if !project_salt_file.exists() {
-                            // TODO: remove_dir fails if directory is not empty
-                            // fix it!
-                            std::fs::remove_dir(project_dir)?;
-                            // this is not a valid salt project
-                            return Err(std::io::Error::new(
-                                std::io::ErrorKind::InvalidData,
-                                "not a valid salt project",
-                            ));
-                        }
-                        // TODO: print some message for the developer
-                        // TODO: we can update the cache here
-                        return Ok(());
+        // TODO: think of a way to check if it is a salt project by just
+        // checking if this remote has salt.json in it.
+        // then only clone it
+        let mut clone_cmd = std::process::Command::new("git");
+        clone_cmd.args(["clone", link, project_dir.to_str().unwrap()]);
+        match clone_cmd.status()?.code() {
+            Some(code) => {
+                if code == 0 {
+                    let project_salt_file = project_dir.join("salt.json");
+                    if !project_salt_file.exists() {
+                        // TODO: remove_dir fails if directory is not empty
+                        // fix it!
+                        std::fs::remove_dir(project_dir)?;
+                        // this is not a valid salt project
+                        return Err(std::io::Error::new(
+                            std::io::ErrorKind::InvalidData,
+                            "not a valid salt project",
+                        ));
                     }
-                    return Err(std::io::Error::new(
-                        std::io::ErrorKind::InvalidData,
-                        "error while cloning project",
-                    ));
-                }
-                None => {
-                    return Err(std::io::Error::new(
-                        std::io::ErrorKind::InvalidData,
-                        "error while cloning project",
-                    ));
+                    // TODO: print some message for the developer
+                    // TODO: we can update the cache here
+                    return Ok(());
                 }
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "error while cloning project",
+                ))
             }
+            None => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "error while cloning project",
+            )),
         }
-        Ok(())
     }
 
     fn display_salt_help(&self, projects: &Vec<ProjectDefinition>) {
@@ -757,7 +1426,7 @@ Salt commands:
             help.push_str(
                 format!(
                     "{} [{}] {}            - {}\n",
-                    project.options.name,
+                    project.qualified_name(),
                     "0.1",
                     if project.is_pinned { "📌" } else { "" },
                     project.help
@@ -2,9 +2,25 @@ use std::{collections::HashMap, path::PathBuf};
 
 use serde::{Deserialize, Serialize};
 
+pub(crate) mod config;
+pub(crate) mod discover;
 pub(crate) mod doc;
+pub(crate) mod editor;
+pub(crate) mod export;
+pub(crate) mod gfm;
+pub(crate) mod highlight;
+pub(crate) mod http;
 pub(crate) mod interface;
+pub(crate) mod overrides;
 pub(crate) mod parser;
+pub(crate) mod paths;
+pub(crate) mod remote;
+pub(crate) mod resolver;
+pub(crate) mod search;
+pub(crate) mod server;
+pub(crate) mod term;
+#[cfg(test)]
+pub(crate) mod test_util;
 
 pub static VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -19,6 +35,11 @@ pub struct Command {
 pub(crate) struct ProjectOpts {
     pub(crate) typ: String,
     pub(crate) name: String,
+    /// template replacing the built in one for the html doc, relative to
+    /// the project
+    pub(crate) template: Option<String>,
+    /// css added to the html doc, relative to the project
+    pub(crate) css: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -30,16 +51,85 @@ pub struct ProjectDefinition {
     pub(crate) commands: HashMap<String, Command>,
     pub(crate) about: String,
     pub(crate) help: String,
+    /// includes are the SALT.md files (relative paths or git links) which a
+    /// bundle pulls in as its member projects
+    pub(crate) includes: Vec<String>,
+    /// name of the bundle this project was loaded through, if any
+    pub(crate) bundle: Option<String>,
+    /// env are the environment variables set for every command of the project
+    pub(crate) env: HashMap<String, String>,
 
     pub is_pinned: bool,
     pub project_path: PathBuf,
     pub exec_path: PathBuf,
 }
 
+impl ProjectDefinition {
+    pub(crate) fn is_bundle(&self) -> bool {
+        self.options.typ == "bundle"
+    }
+
+    /// qualified_name is the name through which the project is addressed
+    /// from the command line, i.e `bundle/project` for bundle members
+    pub(crate) fn qualified_name(&self) -> String {
+        match &self.bundle {
+            Some(bundle) => format!("{}/{}", bundle, self.options.name),
+            None => self.options.name.clone(),
+        }
+    }
+}
+
+/// SaltConfig is the user config stored in the salt config file (see
+/// [paths::SaltPaths]), missing keys fall back to their defaults and unknown
+/// keys are ignored
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
 pub struct SaltConfig {
+    pub version: u64,
     pub editor: Option<String>,
     pub pinned_paths: HashMap<String, String>,
+    pub docs: DocsConfig,
+    pub http: HttpConfig,
+}
+
+/// HttpConfig are the settings of the requests salt makes, like fetching
+/// docs from the web, under `http` in the config
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct HttpConfig {
+    /// seconds a request can take, [http::DEFAULT_TIMEOUT_SECS] when unset
+    pub timeout: Option<u64>,
+    /// proxy for every request, the proxy env variables are used when unset
+    pub proxy: Option<String>,
+    /// pem file of certificates trusted on top of the system ones
+    pub ca_bundle: Option<String>,
+    /// tokens sent to hosts (and their subdomains) as bearer auth, by host
+    pub tokens: HashMap<String, String>,
+}
+
+/// DocsConfig are the settings of the html docs, under `docs` in the config
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct DocsConfig {
+    pub assets: DocAssets,
+    /// template for docs of projects which set none
+    pub template: Option<String>,
+    /// css for docs of projects which set none
+    pub css: Option<String>,
+}
+
+/// DocAssets is where html docs load their styles and scripts from
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DocAssets {
+    /// from public CDNs, which needs a connection to the internet
+    #[default]
+    Cdn,
+    /// from a small stylesheet and script of salt's own written into the
+    /// page, so docs work without a connection. These are not copies of the
+    /// CDN assets, diagrams only show up when graphviz draws them while the
+    /// doc is rendered, others are shown as their source
+    Offline,
 }
 
 pub type ProjectMap = HashMap<String, ProjectDefinition>;
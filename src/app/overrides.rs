@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+use std::io::Result;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use super::{log, Command, ProjectDefinition};
+
+/// LOCAL_SALT_FILE is an uncommitted SALT.md next to the project one, written
+/// in the same format, whose commands, env and name win over the shared file
+pub(crate) static LOCAL_SALT_FILE: &str = "SALT.local.md";
+/// OVERRIDES_FILE is the json form of the same overrides, relative to the
+/// project folder
+static OVERRIDES_FILE: &str = ".salt/overrides.json";
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct Overrides {
+    env: HashMap<String, String>,
+    commands: HashMap<String, CommandOverride>,
+    options: OptionsOverride,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct CommandOverride {
+    command: Option<String>,
+    about: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct OptionsOverride {
+    name: Option<String>,
+    #[serde(rename = "type")]
+    typ: Option<String>,
+}
+
+/// apply_overrides merges the developer's own overrides on top of the project
+/// definition built from the committed SALT.md. SALT.local.md is applied first
+/// and .salt/overrides.json last, so the json file has the final say
+pub(crate) fn apply_overrides(def: &mut ProjectDefinition, project_dir: &Path) -> Result<()> {
+    let local_md = project_dir.join(LOCAL_SALT_FILE);
+    if local_md.is_file() {
+        log!("applying overrides from {:?}", &local_md);
+        let md_str = std::fs::read_to_string(local_md)?;
+        let local = ProjectDefinition::from(markdown::tokenize(&md_str));
+        def.commands.extend(local.commands);
+        def.env.extend(local.env);
+        if !local.options.name.is_empty() {
+            def.options.name = local.options.name;
+        }
+    }
+
+    let overrides_json = project_dir.join(OVERRIDES_FILE);
+    if overrides_json.is_file() {
+        log!("applying overrides from {:?}", &overrides_json);
+        let json_str = std::fs::read_to_string(&overrides_json)?;
+        let overrides = serde_json::from_str::<Overrides>(&json_str).map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "error while reading {}:{}:{} {}",
+                    overrides_json.to_string_lossy(),
+                    e.line(),
+                    e.column(),
+                    e
+                ),
+            )
+        })?;
+        merge_overrides(def, overrides)?;
+    }
+    Ok(())
+}
+
+fn merge_overrides(def: &mut ProjectDefinition, overrides: Overrides) -> Result<()> {
+    def.env.extend(overrides.env);
+    for (key, cmd_override) in overrides.commands {
+        match def.commands.get_mut(&key) {
+            Some(cmd) => {
+                if let Some(command) = cmd_override.command {
+                    cmd.command = command;
+                }
+                if let Some(about) = cmd_override.about {
+                    cmd.about = about;
+                }
+            }
+            None => {
+                let command = match cmd_override.command {
+                    Some(command) => command,
+                    None => {
+                        return Err(std::io::Error::new(
+                            std::io::ErrorKind::InvalidData,
+                            format!("override of new command {} needs a command", key),
+                        ))
+                    }
+                };
+                def.commands.insert(
+                    key,
+                    Command {
+                        about: cmd_override.about.unwrap_or(command.clone()),
+                        command,
+                        args: vec![],
+                    },
+                );
+            }
+        }
+    }
+    if let Some(name) = overrides.options.name {
+        def.options.name = name;
+    }
+    if let Some(typ) = overrides.options.typ {
+        def.options.typ = typ;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::apply_overrides;
+    use crate::app::test_util::TempDir;
+    use crate::app::ProjectDefinition;
+
+    #[test]
+    fn test_apply_overrides() {
+        let dir = TempDir::new("overrides");
+        std::fs::create_dir_all(dir.join(".salt")).unwrap();
+        std::fs::write(
+            dir.join("SALT.local.md"),
+            "## commands\n\n- r - `cargo run -- --port 9000` - runs on my port\n\n## env\n\n- PORT - 9000\n- HOST - localhost\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join(".salt/overrides.json"),
+            r#"{
+                "env": { "PORT": "9090" },
+                "commands": {
+                    "b": { "command": "cargo build --release" },
+                    "t": { "command": "cargo test", "about": "tests" }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let md = "## commands\n\n- r - `cargo run` - runs\n- b - `cargo build` - builds\n\n## options\n\n- name - api\n";
+        let mut def = ProjectDefinition::from(markdown::tokenize(md));
+        apply_overrides(&mut def, &dir).unwrap();
+
+        assert_eq!(def.options.name, "api");
+        assert_eq!(def.commands["r"].command, "cargo run -- --port 9000");
+        assert_eq!(def.commands["b"].command, "cargo build --release");
+        assert_eq!(def.commands["b"].about, "builds");
+        assert_eq!(def.commands["t"].about, "tests");
+        assert_eq!(def.env["PORT"], "9090");
+        assert_eq!(def.env["HOST"], "localhost");
+    }
+}
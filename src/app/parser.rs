@@ -1,9 +1,13 @@
 use std::process::Command as ProcessCommand;
 use std::{collections::HashMap, path::PathBuf};
 
+use super::gfm::is_thematic_break;
 use super::{log, Command, ProjectDefinition, ProjectOpts};
 use markdown::Block;
 
+/// DEFAULT_HELP is the help of a project whose SALT.md has none
+pub(crate) const DEFAULT_HELP: &str = "this is a salt package";
+
 impl From<Vec<markdown::Block>> for ProjectDefinition {
     fn from(value: Vec<markdown::Block>) -> Self {
         let mut def = ProjectDefinition {
@@ -13,10 +17,15 @@ impl From<Vec<markdown::Block>> for ProjectDefinition {
             options: ProjectOpts {
                 typ: "project".into(),
                 name: String::new(),
+                template: None,
+                css: None,
             },
             commands: HashMap::new(),
             about: String::new(),
-            help: String::from("this is a salt package"),
+            help: String::from(DEFAULT_HELP),
+            includes: vec![],
+            bundle: None,
+            env: HashMap::new(),
             is_pinned: false,
             project_path: PathBuf::new(),
             exec_path: PathBuf::new(),
@@ -27,10 +36,16 @@ impl From<Vec<markdown::Block>> for ProjectDefinition {
         // mode 2 = processing docs
         // mode 3 = processing options
         // mode 4 = processing package help
+        // mode 5 = processing bundle includes
+        // mode 6 = processing project environment variables
         let mut mode = 0;
         let mut doc_section = String::new();
         // println!("Values: {:?}", value);
         for block in value {
+            let block = match is_thematic_break(&block) {
+                true => Block::Hr,
+                false => block,
+            };
             match block {
                 Block::OrderedList(li, li_type) => {
                     if !doc_section.is_empty() && mode == 2 {
@@ -94,10 +109,10 @@ impl From<Vec<markdown::Block>> for ProjectDefinition {
                     if hsize == 2_usize && h.len() != 1 {
                         return def;
                     }
-                    if !doc_section.is_empty() && hsize == 4_usize {
+                    if !doc_section.is_empty() && hsize >= 4_usize {
                         def.docs
                             .entry(doc_section.clone())
-                            .and_modify(|e| e.push(Block::Header(h.clone(), 4_usize)));
+                            .and_modify(|e| e.push(Block::Header(h.clone(), hsize)));
                         continue;
                     }
                     if hsize == 3_usize {
@@ -105,7 +120,7 @@ impl From<Vec<markdown::Block>> for ProjectDefinition {
                         match h.first().unwrap() {
                             markdown::Span::Text(t) => {
                                 doc_section = t.to_owned();
-                                def.docs.entry(t.to_owned()).or_insert(Vec::new());
+                                def.docs.entry(t.to_owned()).or_default();
                             }
                             _ => {
                                 return def;
@@ -134,6 +149,14 @@ impl From<Vec<markdown::Block>> for ProjectDefinition {
                                     mode = 3;
                                     continue;
                                 }
+                                "includes" | "include" => {
+                                    mode = 5;
+                                    continue;
+                                }
+                                "env" | "environment" => {
+                                    mode = 6;
+                                    continue;
+                                }
                                 _ => {
                                     return def;
                                 }
@@ -233,6 +256,14 @@ impl From<Vec<markdown::Block>> for ProjectDefinition {
                                             def.options.name =
                                                 splitted.get(1..).unwrap().to_owned().join("-");
                                         }
+                                        "template" => {
+                                            def.options.template =
+                                                Some(splitted.get(1..).unwrap().join("-"));
+                                        }
+                                        "css" => {
+                                            def.options.css =
+                                                Some(splitted.get(1..).unwrap().join("-"));
+                                        }
                                         _ => {
                                             continue;
                                         }
@@ -241,9 +272,68 @@ impl From<Vec<markdown::Block>> for ProjectDefinition {
                                 markdown::ListItem::Paragraph(_) => return def,
                             }
                         }
+                        continue;
+                    }
+                    if mode == 5 {
+                        for item in items {
+                            match item {
+                                markdown::ListItem::Simple(span_vec) => {
+                                    let mut include = String::new();
+                                    for span in span_vec {
+                                        match span {
+                                            markdown::Span::Text(t) => include.push_str(&t),
+                                            markdown::Span::Code(c) => include.push_str(&c),
+                                            // links are written as <https://...> or [name](https://...)
+                                            markdown::Span::Link(_, l, _) => include.push_str(&l),
+                                            _ => return def,
+                                        };
+                                    }
+                                    let include = include.trim();
+                                    if !include.is_empty() {
+                                        def.includes.push(include.to_owned());
+                                    }
+                                }
+                                markdown::ListItem::Paragraph(_) => return def,
+                            }
+                        }
+                        continue;
+                    }
+                    if mode == 6 {
+                        for item in items {
+                            match item {
+                                markdown::ListItem::Simple(span_vec) => {
+                                    let mut env_info = String::new();
+                                    for span in span_vec {
+                                        match span {
+                                            markdown::Span::Text(t) => env_info.push_str(&t),
+                                            markdown::Span::Code(c) => env_info.push_str(&c),
+                                            _ => return def,
+                                        };
+                                    }
+
+                                    // both `KEY - value` and `KEY=value` are accepted
+                                    let (key, value) = match env_info.split_once(" - ") {
+                                        Some(kv) => kv,
+                                        None => match env_info.split_once('=') {
+                                            Some(kv) => kv,
+                                            None => continue,
+                                        },
+                                    };
+                                    def.env
+                                        .insert(key.trim().to_owned(), value.trim().to_owned());
+                                }
+                                markdown::ListItem::Paragraph(_) => return def,
+                            }
+                        }
+                    }
+                }
+                Block::Hr | Block::Raw(_) => {
+                    if !doc_section.is_empty() && mode == 2 {
+                        def.docs
+                            .entry(doc_section.clone())
+                            .and_modify(|e| e.push(block));
                     }
                 }
-                _ => continue,
             }
         }
 
@@ -280,6 +370,16 @@ fn parse_command(cmd: &str, start_from_index: usize) -> Result<ProcessCommand, s
     Ok(pcmd)
 }
 
+/// split_env_block separates the `[KEY=value ...]` block a command can start
+/// with from the command line it sets the environment of
+pub(crate) fn split_env_block(cmd: &str) -> (HashMap<String, String>, &str) {
+    if !cmd.starts_with('[') {
+        return (HashMap::new(), cmd.trim());
+    }
+    let (envs, start_from_index) = parse_envs(cmd);
+    (envs, cmd.get(start_from_index..).unwrap_or("").trim())
+}
+
 fn parse_envs(cmd: &str) -> (HashMap<String, String>, usize) {
     let mut envs = HashMap::new();
     let mut index = 0;
@@ -292,7 +392,7 @@ fn parse_envs(cmd: &str) -> (HashMap<String, String>, usize) {
 
         let mut vars = String::new();
         while c != ']' && index < cmd.len() {
-            vars.push_str(&c.to_string());
+            vars.push(c);
             index += 1;
             c = cmd.chars().nth(index).unwrap();
         }
@@ -301,7 +401,7 @@ fn parse_envs(cmd: &str) -> (HashMap<String, String>, usize) {
             if !kv.contains("=") {
                 continue;
             }
-            let env_kv: Vec<&str> = kv.split("=").into_iter().collect();
+            let env_kv: Vec<&str> = kv.split("=").collect();
             let key = env_kv[0].to_owned();
             let value = env_kv[1].to_owned();
             envs.insert(key, value);
@@ -318,18 +418,30 @@ mod tests {
     use std::ffi::OsStr;
 
     use crate::app::parser::parse_project_command;
+    use crate::app::ProjectDefinition;
 
-    use super::{parse_command, parse_envs};
+    use super::{parse_command, parse_envs, split_env_block};
 
     #[test]
     fn test_basic_parse_command() {
         let c = "go run main.go";
         let cmd_res = parse_command(c, 0);
 
-        assert_eq!(cmd_res.is_ok(), true);
+        assert!(cmd_res.is_ok());
         assert_eq!(cmd_res.unwrap().get_program(), "go");
     }
 
+    #[test]
+    fn test_split_env_block() {
+        let (envs, line) = split_env_block("[A=1 B=2] go run main.go");
+        assert_eq!(line, "go run main.go");
+        assert_eq!(envs.get("B"), Some(&"2".to_owned()));
+
+        let (envs, line) = split_env_block(" make ");
+        assert!(envs.is_empty());
+        assert_eq!(line, "make");
+    }
+
     #[test]
     fn test_basic_parse_envs() {
         let c = "[a=1 b=2]";
@@ -345,15 +457,64 @@ mod tests {
         let c = "[a=1 b=2] go run main.go";
         let parse_res = parse_project_command(c);
 
-        assert_eq!(parse_res.is_ok(), true);
+        assert!(parse_res.is_ok());
 
         assert_eq!(parse_res.as_ref().unwrap().get_program(), "go");
 
-        let first_env = parse_res.as_ref().unwrap().get_envs().nth(0).unwrap();
+        let first_env = parse_res.as_ref().unwrap().get_envs().next().unwrap();
         let second_env = parse_res.as_ref().unwrap().get_envs().nth(1).unwrap();
         assert_eq!(first_env.0, "a");
         assert_eq!(first_env.1.as_ref().unwrap(), &OsStr::new("1"));
         assert_eq!(second_env.0, "b");
         assert_eq!(second_env.1.as_ref().unwrap(), &OsStr::new("2"));
     }
+
+    #[test]
+    fn test_bundle_includes() {
+        let md = r#"## about
+
+platform tooling
+
+## includes
+
+- ./api
+- `../web/SALT.md`
+- https://github.com/team/infra.git
+
+## options
+
+- type - bundle
+- name - platform
+"#;
+        let def = ProjectDefinition::from(markdown::tokenize(md));
+
+        assert!(def.is_bundle());
+        assert_eq!(def.options.name, "platform");
+        assert_eq!(
+            def.includes,
+            vec![
+                "./api".to_owned(),
+                "../web/SALT.md".to_owned(),
+                "https://github.com/team/infra.git".to_owned()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_project_env() {
+        let md = r#"## env
+
+- PORT - 8080
+- `RUST_LOG=debug`
+
+## options
+
+- name - api
+"#;
+        let def = ProjectDefinition::from(markdown::tokenize(md));
+
+        assert_eq!(def.env.get("PORT"), Some(&"8080".to_owned()));
+        assert_eq!(def.env.get("RUST_LOG"), Some(&"debug".to_owned()));
+        assert_eq!(def.options.name, "api");
+    }
 }
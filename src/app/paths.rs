@@ -0,0 +1,198 @@
+use std::io::Result;
+use std::path::{Path, PathBuf};
+
+/// SaltPaths are the locations salt reads and writes its own files from.
+///
+/// By default everything lives inside `$HOME/.salt`. `SALT_HOME` moves all of
+/// it to another folder, and on linux `XDG_CONFIG_HOME`, `XDG_CACHE_HOME` and
+/// `XDG_DATA_HOME` are honoured for the config, cache and data respectively.
+/// A config still at `$HOME/.salt/.config` keeps being used until it is moved
+/// to `XDG_CONFIG_HOME`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SaltPaths {
+    /// the salt config file
+    pub(crate) config_file: PathBuf,
+    /// files which can be recreated at any time, like rendered docs
+    pub(crate) cache_dir: PathBuf,
+    /// files salt owns, like the command history and cloned bundles
+    pub(crate) data_dir: PathBuf,
+}
+
+impl SaltPaths {
+    pub(crate) fn resolve() -> Result<Self> {
+        Self::resolve_with(
+            |key| std::env::var_os(key).map(PathBuf::from),
+            home::home_dir(),
+            |path| path.exists(),
+        )
+    }
+
+    /// resolve_with resolves the paths from the environment given by env and
+    /// the files exists reports, it is split out of [SaltPaths::resolve] so
+    /// that it can be tested without touching the real environment
+    fn resolve_with(
+        env: impl Fn(&str) -> Option<PathBuf>,
+        home: Option<PathBuf>,
+        exists: impl Fn(&Path) -> bool,
+    ) -> Result<Self> {
+        if let Some(salt_home) = env("SALT_HOME") {
+            if !salt_home.is_absolute() {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!(
+                        "SALT_HOME must be an absolute path, it is {}",
+                        salt_home.to_string_lossy()
+                    ),
+                ));
+            }
+            return Ok(Self::from_home(salt_home));
+        }
+        // only absolute paths are valid, as per the XDG base directory spec
+        let env = |key: &str| env(key).filter(|p| p.is_absolute());
+
+        let home = match home {
+            Some(home) => home,
+            None => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    "cannot find your home directory, set SALT_HOME to tell salt where to keep its files",
+                ))
+            }
+        };
+        let mut paths = Self::from_home(home.join(".salt"));
+        if cfg!(target_os = "linux") {
+            if let Some(config_home) = env("XDG_CONFIG_HOME") {
+                let config_file = config_home.join("salt").join("config.json");
+                // configs written before XDG_CONFIG_HOME was set are not lost
+                if exists(&config_file) || !exists(&paths.config_file) {
+                    paths.config_file = config_file;
+                }
+            }
+            if let Some(cache_home) = env("XDG_CACHE_HOME") {
+                paths.cache_dir = cache_home.join("salt");
+            }
+            if let Some(data_home) = env("XDG_DATA_HOME") {
+                paths.data_dir = data_home.join("salt");
+            }
+        }
+        Ok(paths)
+    }
+
+    /// from_home keeps every salt file inside salt_home
+    pub(crate) fn from_home(salt_home: PathBuf) -> Self {
+        SaltPaths {
+            config_file: salt_home.join(".config"),
+            cache_dir: salt_home.clone(),
+            data_dir: salt_home,
+        }
+    }
+
+    pub(crate) fn history_file(&self) -> PathBuf {
+        self.data_dir.join(".history")
+    }
+
+    /// folder where git links included by bundles are cloned
+    pub(crate) fn bundles_dir(&self) -> PathBuf {
+        self.data_dir.join("bundles")
+    }
+
+    /// returns the path of a file inside the cache folder, creating the
+    /// cache folder if it is not there yet
+    pub(crate) fn cache_file(&self, name: &str) -> Result<PathBuf> {
+        std::fs::create_dir_all(&self.cache_dir)?;
+        Ok(self.cache_dir.join(name))
+    }
+}
+
+/// expand_home resolves a `~/` at the start of path to the home folder
+pub(crate) fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), home::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
+
+    use super::SaltPaths;
+
+    fn resolve(vars: &[(&str, &str)]) -> std::io::Result<SaltPaths> {
+        resolve_existing(vars, &[])
+    }
+
+    /// resolve_existing resolves the paths as if only the files in existing
+    /// were there
+    fn resolve_existing(vars: &[(&str, &str)], existing: &[&str]) -> std::io::Result<SaltPaths> {
+        let vars = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), PathBuf::from(v)))
+            .collect::<HashMap<String, PathBuf>>();
+        SaltPaths::resolve_with(
+            |key| vars.get(key).cloned(),
+            Some("/home/salt".into()),
+            |path| existing.iter().any(|e| path == Path::new(e)),
+        )
+    }
+
+    #[test]
+    fn test_default_paths() {
+        assert_eq!(
+            resolve(&[]).unwrap(),
+            SaltPaths::from_home("/home/salt/.salt".into())
+        );
+        assert_eq!(
+            resolve(&[]).unwrap().config_file,
+            PathBuf::from("/home/salt/.salt/.config")
+        );
+        assert!(SaltPaths::resolve_with(|_| None, None, |_| false).is_err());
+    }
+
+    #[test]
+    fn test_salt_home_wins() {
+        let paths =
+            resolve(&[("SALT_HOME", "/tmp/salt"), ("XDG_CACHE_HOME", "/xdg/cache")]).unwrap();
+
+        assert_eq!(paths, SaltPaths::from_home("/tmp/salt".into()));
+        assert_eq!(paths.history_file(), PathBuf::from("/tmp/salt/.history"));
+
+        let err = resolve(&[("SALT_HOME", "relative/salt")]).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_xdg_paths() {
+        let paths = resolve(&[
+            ("XDG_CONFIG_HOME", "/xdg/config"),
+            ("XDG_CACHE_HOME", "/xdg/cache"),
+            ("XDG_DATA_HOME", "relative/is/ignored"),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            paths.config_file,
+            PathBuf::from("/xdg/config/salt/config.json")
+        );
+        assert_eq!(paths.cache_dir, PathBuf::from("/xdg/cache/salt"));
+        assert_eq!(
+            paths.bundles_dir(),
+            PathBuf::from("/home/salt/.salt/bundles")
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_xdg_legacy_config() {
+        let vars = [("XDG_CONFIG_HOME", "/xdg/config")];
+        let legacy = "/home/salt/.salt/.config";
+        let paths = resolve_existing(&vars, &[legacy]).unwrap();
+        assert_eq!(paths.config_file, PathBuf::from(legacy));
+
+        let xdg = "/xdg/config/salt/config.json";
+        let paths = resolve_existing(&vars, &[legacy, xdg]).unwrap();
+        assert_eq!(paths.config_file, PathBuf::from(xdg));
+    }
+}
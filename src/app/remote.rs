@@ -0,0 +1,316 @@
+use std::io::Result;
+
+/// Resolver maps a link to a repository, or to a file or folder in it, to the
+/// raw url of its SALT.md. None when the link is not one it knows
+type Resolver = fn(&Link) -> Option<String>;
+
+/// RESOLVERS are tried in order for links given to `s doc`, add an entry to
+/// fetch docs from another kind of host
+const RESOLVERS: [(&str, Resolver); 5] = [
+    ("github", github),
+    ("gitlab", gitlab),
+    ("bitbucket", bitbucket),
+    ("gitea", gitea),
+    ("raw", raw),
+];
+
+/// GITEA_HOSTS are the public gitea hosts, self hosted ones are recognized
+/// by their `src/branch/...` links
+const GITEA_HOSTS: [&str; 2] = ["codeberg.org", "gitea.com"];
+
+/// Link is a http link split into the parts resolvers look at
+struct Link {
+    /// the link without its query or fragment
+    url: String,
+    /// the query of the link with its `?`, empty when there is none
+    query: String,
+    /// scheme and host, like `https://github.com`
+    origin: String,
+    host: String,
+    /// the non empty parts of the path
+    segments: Vec<String>,
+}
+
+impl Link {
+    fn parse(link: &str) -> Option<Link> {
+        let link = link.split('#').next().unwrap_or_default();
+        let (url, query) = match link.find('?') {
+            Some(i) => link.split_at(i),
+            None => (link, ""),
+        };
+        let (scheme, rest) = url.split_once("://")?;
+        if scheme != "https" && scheme != "http" {
+            return None;
+        }
+        let mut parts = rest.split('/');
+        let host = parts.next().filter(|h| !h.is_empty())?.to_lowercase();
+        Some(Link {
+            url: url.to_owned(),
+            query: query.to_owned(),
+            origin: format!("{}://{}", scheme, host),
+            host: host.trim_start_matches("www.").to_owned(),
+            segments: parts
+                .filter(|s| !s.is_empty())
+                .map(|s| s.to_owned())
+                .collect(),
+        })
+    }
+
+    /// repo is the `owner/repo` a link starts with, without a `.git` suffix
+    fn repo(&self) -> Option<String> {
+        match self.segments.as_slice() {
+            [owner, repo, ..] => Some(format!("{}/{}", owner, repo.trim_end_matches(".git"))),
+            _ => None,
+        }
+    }
+}
+
+/// resolve_doc_url finds the raw url of the SALT.md a link points to.
+///
+/// Links can be to a repository, to a branch, tag or commit of it (the
+/// `tree`/`blob`/`src` links of the host, the ref being one part of the path
+/// so refs with a `/` can not be linked to),
+/// to a folder of it holding a SALT.md or to the SALT.md itself. Repositories
+/// without a ref are read from their default branch. Any other link to a
+/// `.md` file is fetched as it is
+pub(crate) fn resolve_doc_url(link: &str) -> Result<String> {
+    let parsed = Link::parse(link).ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("{} is not a http link", link),
+        )
+    })?;
+    for (_, resolve) in RESOLVERS {
+        if let Some(url) = resolve(&parsed) {
+            return Ok(url);
+        }
+    }
+    Err(std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        format!(
+            "cannot find the SALT.md of {}, link to a GitHub, GitLab, Gitea or Bitbucket repository or to a raw SALT.md",
+            link
+        ),
+    ))
+}
+
+/// doc_path is the path of the SALT.md in a repository, given the path
+/// segments after the ref
+fn doc_path(segments: &[String]) -> String {
+    let path = segments.join("/");
+    if path.is_empty() {
+        "SALT.md".into()
+    } else if path.ends_with(".md") {
+        path
+    } else {
+        format!("{}/SALT.md", path)
+    }
+}
+
+/// with_ref splits `{marker} {ref} {path...}` segments, the ref defaults to
+/// HEAD when there are none
+fn with_ref<'a>(segments: &'a [String], markers: &[&str]) -> Option<(&'a str, &'a [String])> {
+    match segments {
+        [] => Some(("HEAD", segments)),
+        [marker, reference, rest @ ..] if markers.contains(&marker.as_str()) => {
+            Some((reference.as_str(), rest))
+        }
+        _ => None,
+    }
+}
+
+fn github(link: &Link) -> Option<String> {
+    if link.host != "github.com" {
+        return None;
+    }
+    let (reference, path) = with_ref(link.segments.get(2..)?, &["tree", "blob", "raw"])?;
+    Some(format!(
+        "https://raw.githubusercontent.com/{}/{}/{}",
+        link.repo()?,
+        reference,
+        doc_path(path)
+    ))
+}
+
+/// gitlab handles gitlab.com, and self hosted instances by the `/-/` in
+/// their links. Projects can be nested in groups, `group/subgroup/project`
+fn gitlab(link: &Link) -> Option<String> {
+    let split = link.segments.iter().position(|s| s == "-");
+    if link.host != "gitlab.com" && split.is_none() {
+        return None;
+    }
+    let (project, rest) = link.segments.split_at(split.unwrap_or(link.segments.len()));
+    if project.len() < 2 {
+        return None;
+    }
+    let (reference, path) = with_ref(rest.get(1..).unwrap_or_default(), &["tree", "blob", "raw"])?;
+    let project = project.join("/");
+    Some(format!(
+        "{}/{}/-/raw/{}/{}",
+        link.origin,
+        project.trim_end_matches(".git"),
+        reference,
+        doc_path(path)
+    ))
+}
+
+fn bitbucket(link: &Link) -> Option<String> {
+    if link.host != "bitbucket.org" {
+        return None;
+    }
+    let (reference, path) = with_ref(link.segments.get(2..)?, &["src", "raw"])?;
+    Some(format!(
+        "https://bitbucket.org/{}/raw/{}/{}",
+        link.repo()?,
+        reference,
+        doc_path(path)
+    ))
+}
+
+/// gitea handles the public gitea hosts, and self hosted instances by their
+/// `src/branch`, `src/tag` and `src/commit` links
+fn gitea(link: &Link) -> Option<String> {
+    let rest = link.segments.get(2..)?;
+    let kind = match rest {
+        [src, kind, _, ..]
+            if (src == "src" || src == "raw")
+                && ["branch", "tag", "commit"].contains(&kind.as_str()) =>
+        {
+            Some(kind.as_str())
+        }
+        _ => None,
+    };
+    if !GITEA_HOSTS.contains(&link.host.as_str()) && kind.is_none() {
+        return None;
+    }
+    let base = format!("{}/{}/raw", link.origin, link.repo()?);
+    match (kind, rest) {
+        (Some(kind), [_, _, reference, path @ ..]) => Some(format!(
+            "{}/{}/{}/{}",
+            base,
+            kind,
+            reference,
+            doc_path(path)
+        )),
+        // without a ref gitea reads the path from the default branch
+        (None, []) => Some(format!("{}/{}", base, doc_path(&[]))),
+        _ => None,
+    }
+}
+
+/// raw takes links to markdown files as they are, with their query as it
+/// can be what the server needs to find the file
+fn raw(link: &Link) -> Option<String> {
+    match link.segments.last() {
+        Some(last) if last.ends_with(".md") => Some(format!("{}{}", link.url, link.query)),
+        _ => None,
+    }
+}
+
+/// cache_name is a file name for the doc of a remote url
+pub(crate) fn cache_name(url: &str) -> String {
+    let name = url
+        .split_once("://")
+        .map(|(_, rest)| rest)
+        .unwrap_or(url)
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '_' | '-' => c,
+            _ => '-',
+        })
+        .collect::<String>();
+    format!("{}.html", name.trim_matches('-'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cache_name, resolve_doc_url};
+
+    fn resolve(link: &str) -> String {
+        resolve_doc_url(link).unwrap()
+    }
+
+    #[test]
+    fn test_resolve_github() {
+        assert_eq!(
+            resolve("https://github.com/team/api"),
+            "https://raw.githubusercontent.com/team/api/HEAD/SALT.md"
+        );
+        assert_eq!(
+            resolve("https://github.com/team/api.git/"),
+            "https://raw.githubusercontent.com/team/api/HEAD/SALT.md"
+        );
+        assert_eq!(
+            resolve("https://github.com/team/mono/tree/v2/services/api"),
+            "https://raw.githubusercontent.com/team/mono/v2/services/api/SALT.md"
+        );
+        assert_eq!(
+            resolve("https://github.com/team/api/blob/dev/SALT.md#setup"),
+            "https://raw.githubusercontent.com/team/api/dev/SALT.md"
+        );
+        assert_eq!(
+            resolve("https://raw.githubusercontent.com/team/api/dev/SALT.md"),
+            "https://raw.githubusercontent.com/team/api/dev/SALT.md"
+        );
+    }
+
+    #[test]
+    fn test_resolve_gitlab() {
+        assert_eq!(
+            resolve("https://gitlab.com/group/sub/api"),
+            "https://gitlab.com/group/sub/api/-/raw/HEAD/SALT.md"
+        );
+        assert_eq!(
+            resolve("https://gitlab.com/group/api/-/tree/release/docs"),
+            "https://gitlab.com/group/api/-/raw/release/docs/SALT.md"
+        );
+        assert_eq!(
+            resolve("https://git.corp.io/group/api/-/blob/main/SALT.md"),
+            "https://git.corp.io/group/api/-/raw/main/SALT.md"
+        );
+    }
+
+    #[test]
+    fn test_resolve_gitea_and_bitbucket() {
+        assert_eq!(
+            resolve("https://codeberg.org/team/api"),
+            "https://codeberg.org/team/api/raw/SALT.md"
+        );
+        assert_eq!(
+            resolve("https://git.corp.io/team/api/src/tag/v1.0/docs"),
+            "https://git.corp.io/team/api/raw/tag/v1.0/docs/SALT.md"
+        );
+        assert_eq!(
+            resolve("https://bitbucket.org/team/api"),
+            "https://bitbucket.org/team/api/raw/HEAD/SALT.md"
+        );
+        assert_eq!(
+            resolve("https://bitbucket.org/team/api/src/dev/SALT.md"),
+            "https://bitbucket.org/team/api/raw/dev/SALT.md"
+        );
+    }
+
+    #[test]
+    fn test_resolve_other_links() {
+        assert_eq!(
+            resolve("http://docs.corp.io/api/SALT.md?x=1#setup"),
+            "http://docs.corp.io/api/SALT.md?x=1"
+        );
+        assert_eq!(
+            resolve("https://github.com/team/api/blob/dev/SALT.md?plain=1"),
+            "https://raw.githubusercontent.com/team/api/dev/SALT.md"
+        );
+        assert!(resolve_doc_url("https://example.com/team/api").is_err());
+        assert!(resolve_doc_url("https://github.com/team").is_err());
+        assert!(resolve_doc_url("https://github.com/team/api/issues").is_err());
+        assert!(resolve_doc_url("ftp://example.com/SALT.md").is_err());
+    }
+
+    #[test]
+    fn test_cache_name() {
+        assert_eq!(
+            cache_name("https://raw.githubusercontent.com/team/api/HEAD/SALT.md"),
+            "raw.githubusercontent.com-team-api-HEAD-SALT.md.html"
+        );
+    }
+}
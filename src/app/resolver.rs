@@ -0,0 +1,114 @@
+use std::io::Result;
+
+use super::{ProjectDefinition, ProjectMap};
+
+/// resolve_project finds a project from an address given on the command line.
+///
+/// An address is either the qualified name of a project (`bundle/project`,
+/// which can be nested as `bundle/bundle/project`) or any trailing part of it,
+/// so `api` resolves to `platform/api` as long as no other bundle has an `api`
+/// project in it.
+pub(crate) fn resolve_project<'a>(
+    project_map: &'a ProjectMap,
+    address: &str,
+) -> Result<&'a ProjectDefinition> {
+    let address = address.trim_matches('/');
+    if let Some(project) = project_map.get(address) {
+        return Ok(project);
+    }
+
+    let suffix = format!("/{}", address);
+    let mut candidates = project_map
+        .iter()
+        .filter(|(name, _)| name.ends_with(&suffix))
+        .collect::<Vec<(&String, &ProjectDefinition)>>();
+    match candidates.len() {
+        0 => Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("project not found: {}", address),
+        )),
+        1 => Ok(candidates.pop().unwrap().1),
+        _ => {
+            let mut names = candidates
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<&str>>();
+            names.sort();
+            Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "{} is ambiguous, did you mean one of: {}",
+                    address,
+                    names.join(", ")
+                ),
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::resolve_project;
+    use crate::app::{ProjectDefinition, ProjectMap};
+
+    fn project(name: &str, bundle: Option<&str>) -> ProjectDefinition {
+        let md = format!("## options\n\n- type - project\n- name - {}\n", name);
+        let mut def = ProjectDefinition::from(markdown::tokenize(&md));
+        def.bundle = bundle.map(|b| b.to_owned());
+        def
+    }
+
+    fn project_map(projects: Vec<ProjectDefinition>) -> ProjectMap {
+        let mut map = HashMap::new();
+        for p in projects {
+            map.insert(p.qualified_name(), p);
+        }
+        map
+    }
+
+    #[test]
+    fn test_resolve_exact_and_suffix() {
+        let map = project_map(vec![
+            project("salt", None),
+            project("api", Some("platform")),
+            project("db", Some("platform/infra")),
+        ]);
+
+        assert_eq!(resolve_project(&map, "salt").unwrap().options.name, "salt");
+        assert_eq!(
+            resolve_project(&map, "platform/api")
+                .unwrap()
+                .qualified_name(),
+            "platform/api"
+        );
+        assert_eq!(
+            resolve_project(&map, "api").unwrap().qualified_name(),
+            "platform/api"
+        );
+        assert_eq!(
+            resolve_project(&map, "infra/db").unwrap().qualified_name(),
+            "platform/infra/db"
+        );
+        assert!(resolve_project(&map, "web").is_err());
+    }
+
+    #[test]
+    fn test_resolve_ambiguous() {
+        let map = project_map(vec![
+            project("api", Some("payments")),
+            project("api", Some("search")),
+        ]);
+
+        let err = resolve_project(&map, "api").unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        assert!(err.to_string().contains("payments/api, search/api"));
+        assert_eq!(
+            resolve_project(&map, "search/api")
+                .unwrap()
+                .qualified_name(),
+            "search/api"
+        );
+    }
+}
@@ -0,0 +1,202 @@
+use super::export::SearchEntry;
+use super::term::{TermRenderer, BOLD, CYAN, DIM, YELLOW};
+
+/// MAX_RESULTS is how many hits `s search` prints
+const MAX_RESULTS: usize = 20;
+
+/// SNIPPET_WIDTH is the number of characters of a section shown around the
+/// first match
+const SNIPPET_WIDTH: usize = 100;
+
+/// search_terms splits a query into the lowercase terms which are searched
+pub(crate) fn search_terms(query: &[String]) -> Vec<String> {
+    query
+        .iter()
+        .flat_map(|q| q.split_whitespace())
+        .map(|t| t.to_lowercase())
+        .collect()
+}
+
+/// search finds the entries which contain every term, best ranked first
+pub(crate) fn search<'a>(entries: &'a [SearchEntry], terms: &[String]) -> Vec<&'a SearchEntry> {
+    let mut hits = entries
+        .iter()
+        .filter_map(|e| score(e, terms).map(|s| (s, e)))
+        .collect::<Vec<(usize, &SearchEntry)>>();
+    // hits with the same score are ordered by project name, and the sort
+    // being stable keeps the hits of one project in doc order
+    hits.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.project.cmp(&b.1.project)));
+    hits.into_iter().map(|(_, e)| e).collect()
+}
+
+/// score ranks an entry for terms, matches in the name of the section or
+/// project count more than matches in its text. None when a term is missing
+fn score(entry: &SearchEntry, terms: &[String]) -> Option<usize> {
+    if terms.is_empty() {
+        return None;
+    }
+    let text = entry.text.to_lowercase();
+    let section = entry.section.to_lowercase();
+    let project = entry.project.to_lowercase();
+    let mut score = 0;
+    for term in terms {
+        let matches = text.matches(term.as_str()).count()
+            + 5 * section.matches(term.as_str()).count()
+            + 3 * project.matches(term.as_str()).count();
+        if matches == 0 {
+            return None;
+        }
+        score += matches;
+    }
+    // the terms written one after the other, as in "rotate keys"
+    if terms.len() > 1 && text.contains(&terms.join(" ")) {
+        score += 10;
+    }
+    Some(score)
+}
+
+/// snippet is the part of text around the first match of a term, with the
+/// terms styled by style
+pub(crate) fn snippet(text: &str, terms: &[String], style: impl Fn(&str) -> String) -> String {
+    let chars = text.chars().collect::<Vec<char>>();
+    // lowercased char by char so positions stay the same as in text
+    let lower = chars
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect::<Vec<char>>();
+    let terms = terms
+        .iter()
+        .map(|t| t.chars().collect::<Vec<char>>())
+        .filter(|t| !t.is_empty())
+        .collect::<Vec<Vec<char>>>();
+    let match_at = |i: usize| terms.iter().find(|t| lower[i..].starts_with(t));
+
+    let first = (0..lower.len())
+        .find(|i| match_at(*i).is_some())
+        .unwrap_or(0);
+    let mut start = first.saturating_sub(SNIPPET_WIDTH / 3);
+    if start > 0 {
+        // start at a word
+        start = (start..first)
+            .find(|i| chars[*i - 1] == ' ')
+            .unwrap_or(first);
+    }
+    let mut end = (start + SNIPPET_WIDTH).min(chars.len());
+    if end < chars.len() {
+        end = (start..end).rev().find(|i| chars[*i] == ' ').unwrap_or(end);
+    }
+
+    let mut out = String::new();
+    if start > 0 {
+        out.push('…');
+    }
+    let mut i = start;
+    while i < end {
+        match match_at(i) {
+            Some(term) if i + term.len() <= end => {
+                let matched = chars[i..i + term.len()].iter().collect::<String>();
+                out.push_str(&style(&matched));
+                i += term.len();
+            }
+            _ => {
+                out.push(chars[i]);
+                i += 1;
+            }
+        }
+    }
+    if end < chars.len() {
+        out.push('…');
+    }
+    out
+}
+
+/// format_hits lists the hits of a search as `project › section` lines,
+/// each followed by a snippet
+pub(crate) fn format_hits(
+    hits: &[&SearchEntry],
+    terms: &[String],
+    renderer: &TermRenderer,
+) -> String {
+    let mut out = String::new();
+    for hit in hits.iter().take(MAX_RESULTS) {
+        out.push_str(&format!(
+            "{} › {}\n",
+            renderer.style(&hit.project, &[BOLD, CYAN]),
+            renderer.style(&hit.section, &[BOLD])
+        ));
+        let text = snippet(&hit.text, terms, |t| renderer.style(t, &[BOLD, YELLOW]));
+        if !text.is_empty() {
+            out.push_str(&format!("    {}\n", text));
+        }
+        out.push('\n');
+    }
+    if hits.len() > MAX_RESULTS {
+        out.push_str(&renderer.style(
+            &format!(
+                "{} more results, add terms to narrow them down\n",
+                hits.len() - MAX_RESULTS
+            ),
+            &[DIM],
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{search, search_terms, snippet};
+    use crate::app::export::SearchEntry;
+
+    fn entry(project: &str, section: &str, text: &str) -> SearchEntry {
+        SearchEntry {
+            project: project.into(),
+            section: section.into(),
+            link: String::new(),
+            text: text.into(),
+        }
+    }
+
+    #[test]
+    fn test_search() {
+        let entries = vec![
+            entry("api", "Setup", "run make to build, keys are in the vault"),
+            entry(
+                "vault",
+                "Keys",
+                "rotate keys with make rotate, rotate the keys monthly",
+            ),
+            entry("web", "Deploy", "keys rotate on deploy"),
+        ];
+        let terms = search_terms(&["Rotate keys".into()]);
+        assert_eq!(terms, vec!["rotate", "keys"]);
+
+        let hits = search(&entries, &terms)
+            .iter()
+            .map(|e| e.project.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(hits, vec!["vault", "web"]);
+        assert!(search(&entries, &search_terms(&["nothing".into()])).is_empty());
+        assert!(search(&entries, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_snippet() {
+        let style = |t: &str| format!("[{}]", t);
+        let terms = vec!["keys".to_owned()];
+        assert_eq!(
+            snippet("rotate the Keys monthly", &terms, style),
+            "rotate the [Keys] monthly"
+        );
+
+        let long = format!(
+            "{} the keys are here {}",
+            "word ".repeat(40),
+            "tail ".repeat(40)
+        );
+        let text = snippet(&long, &terms, style);
+        assert!(text.starts_with("…word"));
+        assert!(text.contains("the [keys] are here"));
+        assert!(text.ends_with('…'));
+        assert!(text.chars().count() <= 102);
+    }
+}
@@ -0,0 +1,503 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io::{BufRead, BufReader, Read, Result, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::doc::{project_aliases, script_json, site_nav, Doc, DocIndex, Theme};
+use super::export::{is_inside_project, site_search_entries};
+use super::interface::parse_project_from_path;
+use super::overrides::LOCAL_SALT_FILE;
+use super::resolver::resolve_project;
+use super::{log, DocAssets, DocsConfig, ProjectDefinition, ProjectMap};
+
+/// DEFAULT_PORT is the port `s doc --serve` listens on unless `--port` is given
+pub(crate) const DEFAULT_PORT: u16 = 7070;
+
+/// prefix of the address of every project doc
+static PROJECT_PREFIX: &str = "/p/";
+/// prefix of the addresses live reload polls, followed by the page address
+static VERSION_PREFIX: &str = "/__salt/version";
+/// separates the address of a project doc from the path of a file inside the
+/// project folder, like an image the doc shows
+static FILES_SEPARATOR: &str = "/-/";
+
+/// MAX_HEADER_BYTES caps the size of the request line and headers together
+const MAX_HEADER_BYTES: u64 = 16 * 1024;
+/// MAX_HEADERS caps the number of headers a request can have
+const MAX_HEADERS: usize = 64;
+
+/// DocServer serves the docs of every project salt knows over http, the docs
+/// are rendered from the SALT.md on disk for every request so edits show up
+/// on reload
+pub(crate) struct DocServer {
+    project_map: ProjectMap,
+    config: DocsConfig,
+}
+
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn ok(content_type: &'static str, body: impl Into<Vec<u8>>) -> Self {
+        Response {
+            status: "200 OK",
+            content_type,
+            body: body.into(),
+        }
+    }
+
+    fn error(status: &'static str, message: String) -> Self {
+        Response {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: message.into_bytes(),
+        }
+    }
+}
+
+impl DocServer {
+    pub(crate) fn new(project_map: ProjectMap, config: DocsConfig) -> Self {
+        DocServer {
+            project_map,
+            config,
+        }
+    }
+
+    /// project_link is the address the doc of project is served at
+    pub(crate) fn project_link(project: &ProjectDefinition) -> String {
+        format!("{}{}", PROJECT_PREFIX, project.qualified_name())
+    }
+
+    /// serve answers requests on listener until salt is stopped, every
+    /// connection on its own thread so a slow client does not hold up others
+    pub(crate) fn serve(&self, listener: TcpListener) -> Result<()> {
+        let port = listener.local_addr()?.port();
+        std::thread::scope(|scope| {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        log!("failed to accept connection: {}", e);
+                        continue;
+                    }
+                };
+                // a broken connection only affects that one request
+                scope.spawn(move || {
+                    if let Err(e) = self.handle(stream, port) {
+                        log!("failed to answer request: {}", e);
+                    }
+                });
+            }
+        });
+        Ok(())
+    }
+
+    fn handle(&self, mut stream: TcpStream, port: u16) -> Result<()> {
+        // a client which never finishes its request must not hold its thread
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+        let mut request_line = String::new();
+        // nor must one which never stops sending headers fill the memory
+        let mut reader = BufReader::new((&stream).take(MAX_HEADER_BYTES));
+        reader.read_line(&mut request_line)?;
+        let mut host = None;
+        let mut headers = 0;
+        let mut header = String::new();
+        while headers <= MAX_HEADERS && reader.read_line(&mut header)? > 2 {
+            if let Some((name, value)) = header.split_once(':') {
+                if name.trim().eq_ignore_ascii_case("host") {
+                    host = Some(value.trim().to_lowercase());
+                }
+            }
+            headers += 1;
+            header.clear();
+        }
+        let too_large = headers > MAX_HEADERS || reader.get_ref().limit() == 0;
+
+        let mut parts = request_line.split_whitespace();
+        let response = match (parts.next(), parts.next()) {
+            _ if too_large => Response::error(
+                "431 Request Header Fields Too Large",
+                "request headers are too large".into(),
+            ),
+            // pages of other sites could reach the server through a name
+            // resolving to 127.0.0.1, only the server's own address is answered
+            _ if !is_local_host(host.as_deref(), port) => {
+                Response::error("403 Forbidden", "unknown host".into())
+            }
+            (Some("GET"), Some(target)) => self.respond(target),
+            (Some(_), Some(_)) => {
+                Response::error("405 Method Not Allowed", "only GET is supported".into())
+            }
+            _ => Response::error("400 Bad Request", "malformed request".into()),
+        };
+        log!("{} -> {}", request_line.trim(), response.status);
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+            response.status,
+            response.content_type,
+            response.body.len()
+        )?;
+        stream.write_all(&response.body)?;
+        stream.flush()
+    }
+
+    fn respond(&self, target: &str) -> Response {
+        // query strings are not used by any page
+        let path = percent_decode(target.split('?').next().unwrap_or(target));
+        let result = if let Some(page) = path.strip_prefix(VERSION_PREFIX) {
+            self.version(page)
+                .map(|v| Response::ok("text/plain; charset=utf-8", v))
+        } else if path == "/" {
+            self.index()
+                .map(|html| Response::ok("text/html; charset=utf-8", html))
+        } else if let Some((name, file)) = path
+            .strip_prefix(PROJECT_PREFIX)
+            .and_then(|p| p.split_once(FILES_SEPARATOR))
+        {
+            self.project_file(name, file)
+                .map(|bytes| Response::ok(content_type(file), bytes))
+        } else if let Some(name) = path.strip_prefix(PROJECT_PREFIX) {
+            self.project_doc(name)
+                .map(|html| Response::ok("text/html; charset=utf-8", html))
+        } else {
+            return Response::error("404 Not Found", format!("nothing at {}", path));
+        };
+        result.unwrap_or_else(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => Response::error("404 Not Found", e.to_string()),
+            // an address which matches projects of several bundles
+            std::io::ErrorKind::InvalidInput => Response::error("400 Bad Request", e.to_string()),
+            _ => Response::error("500 Internal Server Error", e.to_string()),
+        })
+    }
+
+    fn index(&self) -> Result<String> {
+        let mut index = DocIndex::new(self.project_map.values(), Self::project_link);
+        index.live_reload = true;
+        index.offline = self.config.assets == DocAssets::Offline;
+        index.render_html()
+    }
+
+    fn project_doc(&self, name: &str) -> Result<String> {
+        let project = self.reload(resolve_project(&self.project_map, name)?)?;
+        let image_base = Self::image_base(&project.qualified_name());
+        // the theme is read again as well, so it can be worked on with reloads
+        let theme = Theme::load(&project, &self.config)?;
+        let aliases = project_aliases(self.project_map.values(), &project);
+        let mut doc = Doc::with_image_base(project, &image_base);
+        doc.aliases = aliases;
+        doc.set_theme(theme);
+        doc.live_reload = true;
+        doc.offline = self.config.assets == DocAssets::Offline;
+        doc.home = Some("/".into());
+        doc.nav = site_nav(self.project_map.values(), Self::project_link);
+        // the search box searches every project the server has
+        doc.search_index = script_json(&site_search_entries(&self.project_map, Self::project_link));
+        doc.render_html()
+    }
+
+    /// project_file reads an image the doc of a project shows. Only the
+    /// relative images of its SALT.md are served, and only while they are
+    /// inside the project folder once symlinks are followed
+    fn project_file(&self, name: &str, file: &str) -> Result<Vec<u8>> {
+        let not_found =
+            || std::io::Error::new(std::io::ErrorKind::NotFound, format!("no file at {}", file));
+        let project = self.reload(resolve_project(&self.project_map, name)?)?;
+        let path = Path::new(file);
+        if !is_inside_project(path) {
+            return Err(not_found());
+        }
+        let project_path = project.project_path.canonicalize()?;
+        let image_base = Self::image_base(&project.qualified_name());
+        let doc = Doc::with_image_base(project, &image_base);
+        if !doc.images.iter().any(|image| image == file) {
+            return Err(not_found());
+        }
+        let full_path = project_path.join(path).canonicalize()?;
+        if !full_path.starts_with(&project_path) {
+            return Err(not_found());
+        }
+        std::fs::read(full_path)
+    }
+
+    /// image_base is the address the relative images of the doc of project
+    /// name are served under
+    fn image_base(name: &str) -> String {
+        format!(
+            "{}{}{}",
+            PROJECT_PREFIX,
+            name,
+            FILES_SEPARATOR.trim_end_matches('/')
+        )
+    }
+
+    /// version changes whenever one of the files the page was rendered from
+    /// changes, which is what live reload polls for
+    fn version(&self, page: &str) -> Result<String> {
+        let mut hasher = DefaultHasher::new();
+        if page == "/" {
+            let mut names = self.project_map.keys().collect::<Vec<&String>>();
+            names.sort();
+            for name in names {
+                hash_sources(&self.project_map[name].project_path, &mut hasher);
+            }
+        } else if let Some(name) = page.strip_prefix(PROJECT_PREFIX) {
+            let project = resolve_project(&self.project_map, name)?;
+            hash_sources(&project.project_path, &mut hasher);
+        } else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("no page at {}", page),
+            ));
+        }
+        Ok(hasher.finish().to_string())
+    }
+
+    /// reload parses the SALT.md of project again, keeping the name and bundle
+    /// it was loaded under
+    fn reload(&self, project: &ProjectDefinition) -> Result<ProjectDefinition> {
+        let mut fresh = parse_project_from_path(&project.project_path.join("SALT.md"))?;
+        fresh.options.name = project.options.name.clone();
+        fresh.bundle = project.bundle.clone();
+        fresh.is_pinned = project.is_pinned;
+        fresh.project_path = project.project_path.clone();
+        fresh.exec_path = project.exec_path.clone();
+        Ok(fresh)
+    }
+}
+
+/// hash_sources hashes the modification times of the files a project is
+/// read from, missing files hash the same as long as they stay missing
+fn hash_sources(project_path: &Path, hasher: &mut DefaultHasher) {
+    let sources: [PathBuf; 3] = [
+        project_path.join("SALT.md"),
+        project_path.join(LOCAL_SALT_FILE),
+        project_path.join(".salt").join("overrides.json"),
+    ];
+    for source in sources {
+        std::fs::metadata(&source)
+            .and_then(|m| m.modified())
+            .ok()
+            .hash(hasher);
+    }
+}
+
+/// is_local_host is true for the Host headers naming the server itself,
+/// `localhost` or `127.0.0.1` with the port it listens on
+fn is_local_host(host: Option<&str>, port: u16) -> bool {
+    let (name, host_port) = match host.map(|h| h.rsplit_once(':').ok_or(h)) {
+        Some(Ok((name, host_port))) => (name, host_port.parse::<u16>().ok()),
+        Some(Err(name)) => (name, Some(80)),
+        None => return false,
+    };
+    (name == "localhost" || name == "127.0.0.1") && host_port == Some(port)
+}
+
+/// content_type guesses the type of a project file from its extension
+fn content_type(file: &str) -> &'static str {
+    let extension = Path::new(file)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase());
+    match extension.as_deref() {
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("svg") => "image/svg+xml",
+        Some("webp") => "image/webp",
+        Some("ico") => "image/x-icon",
+        Some("txt" | "md") => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
+/// percent_decode decodes the `%XX` escapes browsers put in addresses,
+/// invalid escapes are kept as they are
+fn percent_decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+            if let Some(byte) = hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+
+    use super::{is_local_host, percent_decode, DocServer, MAX_HEADERS, MAX_HEADER_BYTES};
+    use crate::app::test_util::TempDir;
+    use crate::app::{DocsConfig, ProjectDefinition};
+
+    fn server(dir: &std::path::Path) -> DocServer {
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(
+            dir.join("SALT.md"),
+            "## about\n\nthe api\n\n### Setup\n\nrun it\n\n![logo](img/logo.svg)\n\n## options\n\n- name - api\n",
+        )
+        .unwrap();
+        let mut project = ProjectDefinition::from(markdown::tokenize(
+            &std::fs::read_to_string(dir.join("SALT.md")).unwrap(),
+        ));
+        project.bundle = Some("platform".into());
+        project.project_path = dir.to_path_buf();
+        let mut map = HashMap::new();
+        map.insert(project.qualified_name(), project);
+        DocServer::new(map, DocsConfig::default())
+    }
+
+    fn get(server: &DocServer, target: &str) -> String {
+        request(server, target, |port| format!("localhost:{}", port))
+    }
+
+    /// request asks server for target, with the Host header host gives for
+    /// the port the server listens on
+    fn request(server: &DocServer, target: &str, host: impl Fn(u16) -> String) -> String {
+        send(server, |port| {
+            format!("GET {} HTTP/1.1\r\nHost: {}\r\n\r\n", target, host(port))
+        })
+    }
+
+    /// send writes the raw request built for the port the server listens on
+    /// and returns the response
+    fn send(server: &DocServer, raw: impl Fn(u16) -> String) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut client = TcpStream::connect(addr).unwrap();
+        client.write_all(raw(addr.port()).as_bytes()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        server.handle(stream, addr.port()).unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_serve_docs() {
+        let tmp = TempDir::new("serve");
+        let dir = tmp.join("api");
+        let server = server(&dir);
+
+        let index = get(&server, "/");
+        assert!(index.starts_with("HTTP/1.1 200 OK"));
+        assert!(index.contains(r#"href="/p/platform/api""#));
+        assert!(index.contains("/__salt/version"));
+
+        let doc = get(&server, "/p/api");
+        assert!(doc.starts_with("HTTP/1.1 200 OK"));
+        assert!(doc.contains("Setup"));
+        assert!(doc.contains(r#"src="/p/platform/api/-/img/logo.svg""#));
+
+        // images are served from the project folder, and only from there
+        std::fs::create_dir_all(dir.join("img")).unwrap();
+        std::fs::write(dir.join("img/logo.svg"), "<svg></svg>").unwrap();
+        let image = get(&server, "/p/platform/api/-/img/logo.svg");
+        assert!(image.contains("Content-Type: image/svg+xml"));
+        assert!(image.ends_with("<svg></svg>"));
+        assert!(get(&server, "/p/api/-/../secret").starts_with("HTTP/1.1 404"));
+        // files the doc does not show are not served
+        std::fs::write(dir.join(".env"), "TOKEN=secret").unwrap();
+        assert!(get(&server, "/p/api/-/.env").starts_with("HTTP/1.1 404"));
+        assert!(get(&server, "/p/api/-/SALT.md").starts_with("HTTP/1.1 404"));
+        // nor are images linking out of the project folder
+        #[cfg(unix)]
+        {
+            let outside = tmp.join("outside");
+            std::fs::write(&outside, "secret").unwrap();
+            std::fs::remove_file(dir.join("img/logo.svg")).unwrap();
+            std::os::unix::fs::symlink(&outside, dir.join("img/logo.svg")).unwrap();
+            assert!(get(&server, "/p/api/-/img/logo.svg").starts_with("HTTP/1.1 404"));
+        }
+
+        // the doc is read from disk on every request
+        std::fs::write(
+            dir.join("SALT.md"),
+            "## about\n\nthe api\n\n### Deploy\n\npush it\n\n## options\n\n- name - renamed\n",
+        )
+        .unwrap();
+        let doc = get(&server, "/p/platform/api");
+        assert!(doc.contains("Deploy"));
+
+        assert!(get(&server, "/p/web").starts_with("HTTP/1.1 404"));
+        assert!(get(&server, "/nothing").starts_with("HTTP/1.1 404"));
+        assert!(get(&server, "/__salt/version/p/api").starts_with("HTTP/1.1 200 OK"));
+    }
+
+    #[test]
+    fn test_host_check() {
+        let dir = TempDir::new("host");
+        let server = server(&dir);
+        let ok = request(&server, "/", |port| format!("127.0.0.1:{}", port));
+        assert!(ok.starts_with("HTTP/1.1 200 OK"));
+        for host in ["evil.example:{}", "localhost:1", "localhost", ""] {
+            let response = request(&server, "/", |port| host.replace("{}", &port.to_string()));
+            assert!(response.starts_with("HTTP/1.1 403"), "{}", host);
+        }
+        assert!(is_local_host(Some("localhost"), 80));
+        assert!(!is_local_host(None, 80));
+    }
+
+    #[test]
+    fn test_request_limits() {
+        let dir = TempDir::new("limits");
+        let server = server(&dir);
+        let many = send(&server, |port| {
+            format!(
+                "GET / HTTP/1.1\r\nHost: localhost:{}\r\n{}\r\n",
+                port,
+                "X-Pad: a\r\n".repeat(MAX_HEADERS)
+            )
+        });
+        assert!(many.starts_with("HTTP/1.1 431"));
+        // a header which never ends is only read up to the limit
+        let long = send(&server, |port| {
+            let mut raw = format!("GET / HTTP/1.1\r\nHost: localhost:{}\r\nX-Pad: ", port);
+            raw.extend(std::iter::repeat_n(
+                'a',
+                MAX_HEADER_BYTES as usize - raw.len(),
+            ));
+            raw
+        });
+        assert!(long.starts_with("HTTP/1.1 431"));
+    }
+
+    #[test]
+    fn test_ambiguous_address() {
+        let tmp = TempDir::new("ambiguous");
+        let mut map = HashMap::new();
+        for bundle in ["platform", "tools"] {
+            let mut project = ProjectDefinition::from(markdown::tokenize(
+                "## about\n\nthe api\n\n## options\n\n- name - api\n",
+            ));
+            project.bundle = Some(bundle.into());
+            project.project_path = tmp.join(bundle);
+            map.insert(project.qualified_name(), project);
+        }
+        let server = DocServer::new(map, DocsConfig::default());
+        assert!(get(&server, "/p/api").starts_with("HTTP/1.1 400"));
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("/p/my%20api"), "/p/my api");
+        assert_eq!(percent_decode("/p/100%"), "/p/100%");
+        assert_eq!(percent_decode("/p/%zz"), "/p/%zz");
+    }
+}
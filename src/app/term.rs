@@ -0,0 +1,531 @@
+use std::io::{IsTerminal, Result, Write};
+
+use markdown::{Block, ListItem, Span};
+
+use super::doc::slugify;
+use super::gfm::{parse_table, Align, Table};
+use super::ProjectDefinition;
+
+const RESET: &str = "\x1b[0m";
+pub(crate) const BOLD: &str = "\x1b[1m";
+pub(crate) const DIM: &str = "\x1b[2m";
+const ITALIC: &str = "\x1b[3m";
+const UNDERLINE: &str = "\x1b[4m";
+pub(crate) const CYAN: &str = "\x1b[36m";
+pub(crate) const YELLOW: &str = "\x1b[33m";
+const GREEN: &str = "\x1b[32m";
+
+/// widest docs are wrapped to, long lines are hard to read even on wide
+/// terminals
+const MAX_WIDTH: usize = 100;
+
+/// TermRenderer renders the docs of a project as text for the terminal,
+/// wrapped to width and colored with ansi escapes when color is set
+pub(crate) struct TermRenderer {
+    pub(crate) width: usize,
+    pub(crate) color: bool,
+}
+
+/// Word is a word of a paragraph along with its escapes, width is the
+/// number of columns it takes on screen
+struct Word {
+    text: String,
+    width: usize,
+    /// glued words have no space between them and the previous word
+    glued: bool,
+}
+
+impl TermRenderer {
+    /// for_stdout sizes the renderer to the terminal, colors are turned off
+    /// when stdout is not a terminal or NO_COLOR is set
+    pub(crate) fn for_stdout() -> Self {
+        TermRenderer {
+            width: terminal_width().min(MAX_WIDTH),
+            color: std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+        }
+    }
+
+    /// render renders the whole project, or just the `###` section given
+    pub(crate) fn render(
+        &self,
+        project: &ProjectDefinition,
+        section: Option<&str>,
+    ) -> Result<String> {
+        let mut out = String::new();
+        if let Some(section) = section {
+            let (title, blocks) = find_section(project, section)?;
+            self.render_section(&mut out, title, blocks);
+            return Ok(out);
+        }
+
+        out.push_str(&self.style(&project.qualified_name(), &[BOLD, CYAN]));
+        out.push_str("\n\n");
+        if !project.about.is_empty() {
+            self.push_wrapped(&mut out, &text_words(&project.about), "", "");
+            out.push('\n');
+        }
+        if !project.commands.is_empty() {
+            out.push_str(&self.style("Commands", &[BOLD]));
+            out.push('\n');
+            let mut keys = project.commands.keys().collect::<Vec<&String>>();
+            keys.sort();
+            for key in keys {
+                let cmd = &project.commands[key];
+                out.push_str(&format!(
+                    "  {}  {}\n",
+                    self.style(key, &[YELLOW]),
+                    strip_controls(&cmd.about)
+                ));
+            }
+            out.push('\n');
+        }
+        for (title, blocks) in &project.docs {
+            self.render_section(&mut out, title, blocks);
+        }
+        Ok(out)
+    }
+
+    fn render_section(&self, out: &mut String, title: &str, blocks: &[Block]) {
+        out.push_str(&self.style(title, &[BOLD, CYAN]));
+        out.push('\n');
+        out.push_str(&self.style(&"─".repeat(title.chars().count()), &[CYAN]));
+        out.push_str("\n\n");
+        self.render_blocks(out, blocks, "");
+    }
+
+    fn render_blocks(&self, out: &mut String, blocks: &[Block], indent: &str) {
+        for block in blocks {
+            match block {
+                Block::Header(spans, _) => {
+                    let words = self.span_words(spans, &[BOLD]);
+                    self.push_wrapped(out, &words, indent, indent);
+                    out.push('\n');
+                }
+                Block::Paragraph(spans) => {
+                    match parse_table(spans) {
+                        Some(table) => self.render_table(out, &table, indent),
+                        None => {
+                            self.push_wrapped(out, &self.span_words(spans, &[]), indent, indent)
+                        }
+                    }
+                    out.push('\n');
+                }
+                Block::Blockquote(blocks) => {
+                    let mut quoted = String::new();
+                    self.render_blocks(&mut quoted, blocks, "");
+                    let bar = self.style("│ ", &[DIM]);
+                    for line in quoted.trim_end().lines() {
+                        out.push_str(&format!("{}{}{}\n", indent, bar, line));
+                    }
+                    out.push('\n');
+                }
+                Block::CodeBlock(_, code) => {
+                    for line in code.trim_end().lines() {
+                        out.push_str(&format!("{}    {}\n", indent, self.style(line, &[GREEN])));
+                    }
+                    out.push('\n');
+                }
+                Block::OrderedList(items, _) => {
+                    for (i, item) in items.iter().enumerate() {
+                        self.render_item(out, item, &format!("{}. ", i + 1), indent);
+                    }
+                    out.push('\n');
+                }
+                Block::UnorderedList(items) => {
+                    for item in items {
+                        self.render_item(out, item, "• ", indent);
+                    }
+                    out.push('\n');
+                }
+                Block::Hr => {
+                    out.push_str(&self.style(&"─".repeat(self.width), &[DIM]));
+                    out.push_str("\n\n");
+                }
+                Block::Raw(raw) => {
+                    out.push_str(&strip_controls(raw));
+                    out.push_str("\n\n");
+                }
+            }
+        }
+    }
+
+    /// render_table lines the cells of a table up in columns, tables are not
+    /// wrapped
+    fn render_table(&self, out: &mut String, table: &Table, indent: &str) {
+        let head = self.table_row(&table.head, &[BOLD]);
+        let rows = table
+            .rows
+            .iter()
+            .map(|row| self.table_row(row, &[]))
+            .collect::<Vec<Vec<(String, usize)>>>();
+        let mut widths = head.iter().map(|(_, w)| *w).collect::<Vec<usize>>();
+        for row in &rows {
+            for (i, (_, width)) in row.iter().enumerate() {
+                widths[i] = widths[i].max(*width);
+            }
+        }
+
+        let push_row = |out: &mut String, row: &[(String, usize)]| {
+            out.push_str(indent);
+            for (i, (cell, width)) in row.iter().enumerate() {
+                let padding = " ".repeat(widths[i] - width);
+                let cell = match table.align[i] {
+                    Align::Right => format!("{}{}", padding, cell),
+                    _ => format!("{}{}", cell, padding),
+                };
+                out.push_str(&cell);
+                if i + 1 < row.len() {
+                    out.push_str(" │ ");
+                }
+            }
+            out.push('\n');
+        };
+        push_row(out, &head);
+        let rule = widths
+            .iter()
+            .map(|w| "─".repeat(*w))
+            .collect::<Vec<String>>()
+            .join("─┼─");
+        out.push_str(&format!("{}{}\n", indent, self.style(&rule, &[DIM])));
+        for row in &rows {
+            push_row(out, row);
+        }
+    }
+
+    /// table_row renders every cell of a row on one line, with its width
+    fn table_row(&self, cells: &[Vec<Span>], styles: &[&str]) -> Vec<(String, usize)> {
+        cells
+            .iter()
+            .map(|cell| {
+                let mut text = String::new();
+                let mut width = 0;
+                for word in self.span_words(cell, styles) {
+                    if !word.glued && !text.is_empty() {
+                        text.push(' ');
+                        width += 1;
+                    }
+                    text.push_str(&word.text);
+                    width += word.width;
+                }
+                (text, width)
+            })
+            .collect()
+    }
+
+    fn render_item(&self, out: &mut String, item: &ListItem, bullet: &str, indent: &str) {
+        let first = format!("{}{}", indent, bullet);
+        let rest = format!("{}{}", indent, " ".repeat(bullet.chars().count()));
+        match item {
+            ListItem::Simple(spans) => {
+                self.push_wrapped(out, &self.span_words(spans, &[]), &first, &rest);
+            }
+            ListItem::Paragraph(blocks) => {
+                let mut item_out = String::new();
+                self.render_blocks(&mut item_out, blocks, &rest);
+                out.push_str(&first);
+                // the blocks of an item, like a nested list, are kept together
+                let lines = item_out
+                    .trim_start()
+                    .lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .collect::<Vec<&str>>();
+                out.push_str(&lines.join("\n"));
+                out.push('\n');
+            }
+        }
+    }
+
+    /// push_wrapped writes words wrapped to the renderer width, the first line
+    /// starts with first and every other line with rest
+    fn push_wrapped(&self, out: &mut String, words: &[Word], first: &str, rest: &str) {
+        let mut line_width = first.chars().count();
+        out.push_str(first);
+        let mut line_empty = true;
+        for word in words {
+            let space = usize::from(!word.glued && !line_empty);
+            if !line_empty && !word.glued && line_width + space + word.width > self.width {
+                out.push('\n');
+                out.push_str(rest);
+                line_width = rest.chars().count();
+                line_empty = true;
+            }
+            if !word.glued && !line_empty {
+                out.push(' ');
+                line_width += 1;
+            }
+            out.push_str(&word.text);
+            line_width += word.width;
+            line_empty = false;
+        }
+        out.push('\n');
+    }
+
+    fn span_words(&self, spans: &[Span], styles: &[&str]) -> Vec<Word> {
+        let mut words = vec![];
+        // a span starts glued to the previous word unless whitespace is
+        // between them, like in "`salt`'s"
+        let mut glue_next = false;
+        for span in spans {
+            let span_words = match span {
+                Span::Break => {
+                    glue_next = false;
+                    continue;
+                }
+                Span::Text(t) if t.starts_with("<!--") => continue,
+                Span::Text(t) => self.styled_words(t, styles),
+                Span::Code(c) => self.styled_words(c, &[styles, &[YELLOW]].concat()),
+                Span::Emphasis(spans) => self.span_words(spans, &[styles, &[ITALIC]].concat()),
+                Span::Strong(spans) => self.span_words(spans, &[styles, &[BOLD]].concat()),
+                Span::Link(text, url, _) => {
+                    let mut link = self.styled_words(text, &[styles, &[UNDERLINE]].concat());
+                    if text != url {
+                        link.extend(self.styled_words(&format!("({})", url), &[DIM]));
+                    }
+                    link
+                }
+                Span::Image(alt, url, _) => {
+                    self.styled_words(&format!("[image: {}] ({})", alt, url), &[DIM])
+                }
+            };
+            let starts_with_space = match span {
+                Span::Text(t) => t.starts_with(char::is_whitespace),
+                _ => false,
+            };
+            let ends_with_space = match span {
+                Span::Text(t) => t.ends_with(char::is_whitespace),
+                _ => false,
+            };
+            for (i, mut word) in span_words.into_iter().enumerate() {
+                if i == 0 && glue_next && !starts_with_space && !words.is_empty() {
+                    word.glued = true;
+                }
+                words.push(word);
+            }
+            glue_next = !ends_with_space;
+        }
+        words
+    }
+
+    fn styled_words(&self, text: &str, styles: &[&str]) -> Vec<Word> {
+        strip_controls(text)
+            .split_whitespace()
+            .map(|w| Word {
+                text: self.style(w, styles),
+                width: w.chars().count(),
+                glued: false,
+            })
+            .collect()
+    }
+
+    /// style colors text with styles, control characters of text are left
+    /// out so that only the escapes of the renderer reach the terminal
+    pub(crate) fn style(&self, text: &str, styles: &[&str]) -> String {
+        let text = strip_controls(text);
+        if !self.color || styles.is_empty() {
+            return text;
+        }
+        format!("{}{}{}", styles.concat(), text, RESET)
+    }
+}
+
+fn text_words(text: &str) -> Vec<Word> {
+    strip_controls(text)
+        .split_whitespace()
+        .map(|w| Word {
+            text: w.to_owned(),
+            width: w.chars().count(),
+            glued: false,
+        })
+        .collect()
+}
+
+/// strip_controls leaves out the control characters of text but newlines
+/// and tabs, a doc could otherwise move the cursor, change colors or set the
+/// title of the terminal it is read in
+fn strip_controls(text: &str) -> String {
+    text.chars()
+        .filter(|c| !c.is_control() || *c == '\n' || *c == '\t')
+        .collect()
+}
+
+/// find_section finds a `###` section of the project docs by its title,
+/// ignoring case
+pub(crate) fn find_section<'a>(
+    project: &'a ProjectDefinition,
+    section: &str,
+) -> Result<(&'a String, &'a Vec<Block>)> {
+    project
+        .docs
+        .iter()
+        .find(|(title, _)| {
+            title.eq_ignore_ascii_case(section.trim()) || slugify(title) == section.trim()
+        })
+        .ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!(
+                    "section {} not found in {}, sections are: {}",
+                    section,
+                    project.qualified_name(),
+                    project
+                        .docs
+                        .keys()
+                        .map(|k| k.as_str())
+                        .collect::<Vec<&str>>()
+                        .join(", ")
+                ),
+            )
+        })
+}
+
+/// terminal_width is taken from COLUMNS, then from `tput cols`, and is 80
+/// when neither knows it
+fn terminal_width() -> usize {
+    if let Some(cols) = std::env::var("COLUMNS")
+        .ok()
+        .and_then(|c| c.trim().parse::<usize>().ok())
+    {
+        return cols;
+    }
+    std::process::Command::new("tput")
+        .arg("cols")
+        .stderr(std::process::Stdio::null())
+        .output()
+        .ok()
+        .and_then(|o| {
+            String::from_utf8_lossy(&o.stdout)
+                .trim()
+                .parse::<usize>()
+                .ok()
+        })
+        .filter(|cols| *cols > 0)
+        .unwrap_or(80)
+}
+
+/// page shows text through `$PAGER` (`less -R` by default) when stdout is a
+/// terminal, and prints it as is otherwise
+pub(crate) fn page(text: &str) -> Result<()> {
+    if !std::io::stdout().is_terminal() {
+        print!("{}", text);
+        return Ok(());
+    }
+    let pager = std::env::var("PAGER")
+        .ok()
+        .filter(|p| !p.trim().is_empty())
+        .unwrap_or("less -R".into());
+    let mut parts = pager.split_whitespace();
+    let mut child = match std::process::Command::new(parts.next().unwrap())
+        .args(parts)
+        .stdin(std::process::Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        // no pager installed, print it all instead
+        Err(_) => {
+            print!("{}", text);
+            return Ok(());
+        }
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        // the pager closing early (e.g. `q` in less) is not an error
+        let _ = stdin.write_all(text.as_bytes());
+    }
+    child.wait()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{strip_controls, TermRenderer};
+    use crate::app::ProjectDefinition;
+
+    fn project() -> ProjectDefinition {
+        let md = "## about\n\nthe api\n\n## commands\n\n- b - `cargo build` - builds\n\n### Setup\n\nRun `make` first, then read the *whole* guide before starting anything else.\n\n> careful\n\n- one\n- two\n\n```\nmake\n```\n\n### Deploy\n\npush it\n\n## options\n\n- name - api\n";
+        ProjectDefinition::from(markdown::tokenize(md))
+    }
+
+    #[test]
+    fn test_render_wraps_plain_text() {
+        let renderer = TermRenderer {
+            width: 30,
+            color: false,
+        };
+        let out = renderer.render(&project(), Some("setup")).unwrap();
+
+        assert!(out.starts_with("Setup\n─────\n\n"));
+        assert!(out.contains("Run make first, then read the\nwhole guide before starting\n"));
+        assert!(out.contains("│ careful\n"));
+        assert!(out.contains("• one\n• two\n"));
+        assert!(out.contains("    make\n"));
+        assert!(!out.contains("Deploy"));
+        assert!(out.lines().all(|l| l.chars().count() <= 30));
+    }
+
+    #[test]
+    fn test_render_nested_lists() {
+        let renderer = TermRenderer {
+            width: 80,
+            color: false,
+        };
+        let md = "### Lists\n\n- one\n    - nested\n    - two\n- three\n\n1. first\n    1. inner\n\nafter\n";
+        let out = renderer
+            .render(&ProjectDefinition::from(markdown::tokenize(md)), None)
+            .unwrap();
+
+        assert!(
+            out.contains("• one\n  • nested\n  • two\n• three\n\n1. first\n   1. inner\n\nafter\n")
+        );
+    }
+
+    #[test]
+    fn test_render_table() {
+        let renderer = TermRenderer {
+            width: 80,
+            color: false,
+        };
+        let md = "### Ports\n\n| service | port |\n|---|--:|\n| api | 8080 |\n| db | 5432 |\n";
+        let out = renderer
+            .render(&ProjectDefinition::from(markdown::tokenize(md)), None)
+            .unwrap();
+
+        assert!(out.contains("service │ port\n────────┼─────\napi     │ 8080\ndb      │ 5432\n"));
+    }
+
+    #[test]
+    fn test_render_whole_project() {
+        let renderer = TermRenderer {
+            width: 80,
+            color: true,
+        };
+        let out = renderer.render(&project(), None).unwrap();
+
+        assert!(out.contains("the api"));
+        assert!(out.contains("\x1b[33mb\x1b[0m  builds"));
+        assert!(out.contains("\x1b[33mmake\x1b[0m first"));
+        assert!(out.contains("Deploy"));
+        assert!(renderer.render(&project(), Some("Nope")).is_err());
+    }
+
+    #[test]
+    fn test_render_strips_control_characters() {
+        let renderer = TermRenderer {
+            width: 80,
+            color: true,
+        };
+        let md = "## about\n\nthe \x1b]0;owned\x07api\n\n## commands\n\n- b - `make` - builds \x1b[2J\n\n### Setup\n\nrun \x1b[31m`make`\u{9b}2J\n\n```\nmake\x08\x08\tall\n```\n\n<div>\x1b[8m</div>\n\n## options\n\n- name - api\n";
+        let out = renderer
+            .render(&ProjectDefinition::from(markdown::tokenize(md)), None)
+            .unwrap();
+
+        assert!(out.contains("the ]0;ownedapi"));
+        assert!(out.contains("builds [2J"));
+        assert!(out.contains("make\tall"));
+        assert!(!out.contains('\u{9b}'));
+        assert!(!out.contains('\x07') && !out.contains('\x08'));
+        // only the escapes of the renderer itself are left
+        for escape in ["\x1b]0", "\x1b[2J", "\x1b[31m", "\x1b[8m"] {
+            assert!(!out.contains(escape), "{:?}", escape);
+        }
+        assert_eq!(strip_controls("a\x1b[1mb\r\n\tc"), "a[1mb\n\tc");
+    }
+}
@@ -0,0 +1,30 @@
+use std::path::{Path, PathBuf};
+
+/// TempDir is a folder for the files of a test, it is removed when it goes
+/// out of scope, so a failing test does not leave it behind either
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    /// new creates an empty folder named after the test, the process id keeps
+    /// concurrent runs of the tests apart
+    pub(crate) fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("salt-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+}
+
+impl std::ops::Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
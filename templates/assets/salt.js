@@ -0,0 +1,52 @@
+// switches between the sections of a doc without bootstrap or jquery, for
+// reading docs offline. No diagram renderer is built in, diagrams which were
+// not drawn while rendering the doc are shown as their source
+(function () {
+    function show(id) {
+        const target = document.getElementById(id);
+        const pane = target && target.closest(".tab-pane");
+        if (!pane) {
+            return;
+        }
+        id = pane.id;
+        document.querySelectorAll("#v-pills-tab [data-toggle=pill]").forEach(function (tab) {
+            tab.classList.toggle("active", tab.getAttribute("href") === "#" + id);
+        });
+        document.querySelectorAll(".tab-content > .tab-pane").forEach(function (other) {
+            other.classList.toggle("active", other === pane);
+            other.classList.toggle("show", other === pane);
+        });
+    }
+
+    document.querySelectorAll("#v-pills-tab [data-toggle=pill]").forEach(function (tab) {
+        tab.addEventListener("click", function (event) {
+            event.preventDefault();
+            show(tab.getAttribute("href").slice(1));
+        });
+    });
+
+    // links to a section or heading of the doc end with its id, show the
+    // section it is in
+    function show_hash() {
+        if (location.hash) {
+            const id = decodeURIComponent(location.hash.slice(1));
+            show(id);
+            const target = document.getElementById(id);
+            if (target && !target.classList.contains("tab-pane")) {
+                target.scrollIntoView();
+            }
+        }
+    }
+    show_hash();
+    window.addEventListener("hashchange", show_hash);
+})();
+
+// without the graphviz and mermaid renderers diagrams are shown as their
+// source
+function draw_into_element(source, element_code) {
+    const pre = document.createElement("pre");
+    pre.textContent = source;
+    document.getElementById(element_code).appendChild(pre);
+}
+
+const draw_mermaid_into_element = draw_into_element;